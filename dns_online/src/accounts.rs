@@ -0,0 +1,149 @@
+use crate::api::{query_available_domains, Domain};
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::resolver::ZoneResolver;
+
+/// The domains of several online.net accounts, each with its own API key.
+/// As every domain carries the API key of its account, the operations on the domains returned by
+/// resolve are automatically sent to the account owning them.
+/// A domain present in several accounts is ambiguous: it is left out of the merged list, and
+/// resolving a name belonging to it fails with Error::DuplicateDomain (see duplicates).
+#[derive(Clone, Debug)]
+pub struct Accounts<'a> {
+    resolver: ZoneResolver<'a>,
+    duplicates: Vec<DomainName>,
+}
+
+impl<'a> Accounts<'a> {
+    /// Query and merge the domains of every account in 'api_keys'. An account given several
+    /// times is only queried once, so that its domains don't look duplicated.
+    pub fn query(api_keys: &[&'a str]) -> Result<Self, Error> {
+        let mut domains = Vec::new();
+        for (i, api_key) in api_keys.iter().enumerate() {
+            if api_keys[..i].contains(api_key) {
+                continue;
+            }
+            domains.extend(query_available_domains(api_key)?);
+        }
+        Ok(Self::from_domains(domains))
+    }
+
+    /// Merge the domains of several accounts.
+    pub fn from_domains(domains: Vec<Domain<'a>>) -> Self {
+        let mut duplicates = Vec::new();
+        for (i, domain) in domains.iter().enumerate() {
+            let origin = domain.origin();
+            if !duplicates.contains(&origin)
+                && domains[i + 1..].iter().any(|d| d.origin() == origin)
+            {
+                duplicates.push(origin);
+            }
+        }
+        let domains = domains
+            .into_iter()
+            .filter(|d| !duplicates.contains(&d.origin()))
+            .collect();
+
+        Accounts {
+            resolver: ZoneResolver::new(domains),
+            duplicates,
+        }
+    }
+
+    /// Ignore the domains hosted outside of online.net. See ZoneResolver::skip_external.
+    pub fn skip_external(mut self, skip: bool) -> Self {
+        self.resolver = self.resolver.skip_external(skip);
+        self
+    }
+
+    /// The domains of all the accounts, without the duplicates.
+    pub fn domains(&self) -> &[Domain<'a>] {
+        self.resolver.domains()
+    }
+
+    /// The names of the domains present in several accounts.
+    pub fn duplicates(&self) -> &[DomainName] {
+        &self.duplicates
+    }
+
+    /// Return the most specific domain containing 'name' among all the accounts, along with
+    /// the part of the name relative to that domain (see ZoneResolver::resolve).
+    /// Fail with Error::DuplicateDomain if the most specific zone containing 'name' is present
+    /// in several accounts.
+    pub fn resolve(&self, name: &DomainName) -> Result<Option<(&Domain<'a>, String)>, Error> {
+        let resolved = self.resolver.resolve(name);
+        let depth = resolved
+            .as_ref()
+            .map(|(d, _)| d.origin().labels().count())
+            .unwrap_or(0);
+        let duplicate = self
+            .duplicates
+            .iter()
+            .filter(|d| name.is_subdomain_of(d))
+            .max_by_key(|d| d.labels().count());
+        match duplicate {
+            Some(d) if d.labels().count() > depth => Err(Error::DuplicateDomain(d.to_string())),
+            _ => Ok(resolved),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(api_key: &'static str, name: &str) -> Domain<'static> {
+        Domain {
+            api_key,
+            id: 0,
+            name: name.into(),
+            dnssec: false,
+            external: false,
+            lock: None,
+        }
+    }
+
+    fn accounts(domains: &[(&'static str, &str)]) -> Accounts<'static> {
+        Accounts::from_domains(domains.iter().map(|(k, d)| domain(k, d)).collect())
+    }
+
+    #[test]
+    fn duplicates_are_excluded() {
+        let accounts = accounts(&[
+            ("key1", "example.com"),
+            ("key1", "example.org"),
+            ("key2", "example.com"),
+        ]);
+        let names: Vec<&str> = accounts.domains().iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["example.org"]);
+        assert_eq!(accounts.duplicates(), &[DomainName::new("example.com")]);
+    }
+
+    #[test]
+    fn name_under_a_duplicate() {
+        let accounts = accounts(&[("key1", "example.com"), ("key2", "example.com")]);
+        match accounts.resolve(&DomainName::new("www.example.com")) {
+            Err(Error::DuplicateDomain(name)) => assert_eq!(name, "example.com"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn more_specific_zone_wins_over_a_duplicate() {
+        let accounts = accounts(&[
+            ("key1", "example.com"),
+            ("key2", "example.com"),
+            ("key2", "sub.example.com"),
+        ]);
+        let (domain, local) = accounts
+            .resolve(&DomainName::new("www.sub.example.com"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(domain.name, "sub.example.com");
+        assert_eq!(domain.api_key, "key2");
+        assert_eq!(local, "www");
+        assert!(accounts
+            .resolve(&DomainName::new("www.example.com"))
+            .is_err());
+    }
+}
@@ -1,11 +1,19 @@
 use serde::de::Visitor;
 use serde::Deserializer;
 use serde_derive::*;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::SystemTime;
 
 use crate::bind::to_bind;
+use crate::diff::ZoneDiff;
 use crate::error::Error;
+use crate::lock::LockConfig;
+use crate::name::{self, DomainName};
 use crate::net::*;
+use crate::query::RecordQuery;
+use crate::resolver::ZoneResolver;
+use crate::set::{self, RecordKey};
 
 // So trivial, right ! (actually, this is a rather convolved way of doing something simple)
 // This artefact is solely necessary as a byproduct of some tiny issues in the API. Indeed,
@@ -37,6 +45,43 @@ where
     deserializer.deserialize_any(UsizeVisitor)
 }
 
+// Same as deserialize_ttl, for the priority of the records, which is also missing (or null)
+// for the record types that don't have any.
+fn deserialize_priority<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct PriorityVisitor;
+    impl<'de> Visitor<'de> for PriorityVisitor {
+        type Value = Option<usize>;
+
+        fn expecting(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+            fmt.write_str("usize compatible type or null")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_u64<E>(self, val: u64) -> Result<Self::Value, E> {
+            Ok(Some(val as usize))
+        }
+
+        fn visit_i64<E>(self, val: i64) -> Result<Self::Value, E> {
+            Ok(Some(val as usize))
+        }
+
+        fn visit_str<E>(self, val: &str) -> Result<Self::Value, E> {
+            Ok(val.parse().ok())
+        }
+    }
+    deserializer.deserialize_any(PriorityVisitor)
+}
+
 /// A DNS domain.
 /// For API design reasons, we also store the API key inside the domain.
 #[derive(Deserialize, Clone, Debug)]
@@ -47,6 +92,9 @@ pub struct Domain<'a> {
     pub name: String,
     pub dnssec: bool,
     pub external: bool,
+    /// Where to take the lock of the domain before modifying it, if at all (see with_lock)
+    #[serde(skip)]
+    pub lock: Option<LockConfig>,
 }
 
 /// A DNS entry.
@@ -60,44 +108,149 @@ pub struct Record {
     #[serde(deserialize_with = "deserialize_ttl")]
     pub ttl: usize,
     pub data: String,
+    /// The preference of MX records and the priority of SRV records, when the API returns it
+    /// apart from the data
+    #[serde(default, alias = "aux", deserialize_with = "deserialize_priority")]
+    pub priority: Option<usize>,
 }
 
 impl Record {
+    /// Create a new record. Internationalized names, including the one a CNAME, NS, MX or SRV
+    /// record points to, are converted to their ASCII form.
     pub fn new(
         entry_name: impl Into<String>,
         entry_type: impl Into<DNSType>,
         entry_value: impl Into<String>,
         entry_ttl: usize,
     ) -> Record {
+        let record_type = entry_type.into();
         Record {
             // The id doesn't actually matter, it isn't passed on to the online.net API
             id: 0,
-            name: entry_name.into(),
-            record_type: entry_type.into(),
+            name: {
+                let name = entry_name.into();
+                name::to_ascii(&name).unwrap_or(name)
+            },
+            record_type,
             ttl: entry_ttl,
-            data: entry_value.into(),
+            data: target_to_ascii(record_type, entry_value.into()),
+            priority: None,
+        }
+    }
+
+    /// The absolute name of this record, given the origin of the zone it belongs to.
+    pub fn domain_name(&self, origin: &DomainName) -> DomainName {
+        DomainName::from_relative(&self.name, origin)
+    }
+
+    /// The identity of this record (name, type and normalised data, including the priority of
+    /// MX and SRV records), given the origin of the zone it belongs to. See RecordKey.
+    pub fn key(&self, origin: &DomainName) -> RecordKey {
+        RecordKey {
+            name: self.domain_name(origin),
+            record_type: self.record_type,
+            data: set::normalise_record_data(self, origin),
+        }
+    }
+
+    /// Return true if both records have the same identity, regardless of their id and ttl.
+    pub fn same_as(&self, other: &Record, origin: &DomainName) -> bool {
+        self.key(origin) == other.key(origin)
+    }
+
+    /// The name of this record in its Unicode form, for display purposes.
+    pub fn unicode_name(&self) -> String {
+        name::to_unicode(&self.name)
+    }
+}
+
+/// Convert the name the data of a CNAME, NS, MX or SRV record points to (its last field) to
+/// its ASCII form. Names that cannot be converted are kept as is.
+fn target_to_ascii(record_type: DNSType, data: String) -> String {
+    match record_type {
+        DNSType::CNAME | DNSType::NS | DNSType::MX | DNSType::SRV if !data.is_ascii() => {
+            let mut fields: Vec<String> = data.split_whitespace().map(String::from).collect();
+            if let Some(target) = fields.last_mut() {
+                if let Ok(ascii) = name::to_ascii(target) {
+                    *target = ascii;
+                }
+            }
+            fields.join(" ")
         }
+        _ => data,
     }
 }
 
+/// The priority to send to the API along with 'record': the one of MX and SRV records (see
+/// set::priority_and_fields), and an arbitrary one for the others, as the API wants a priority
+/// even for the records that don't use it.
+fn api_priority(record: &Record) -> usize {
+    match record.record_type {
+        DNSType::MX | DNSType::SRV => set::priority_and_fields(record).0,
+        _ => record.priority.unwrap_or(12),
+    }
+}
+
+/// Parse a date returned by the API, which may or may not hold a timezone (in which case
+/// UTC is assumed).
+fn parse_api_time(date: &str) -> Option<SystemTime> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date) {
+        return Some(date.into());
+    }
+    let date = chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()?;
+    Some(date.and_utc().into())
+}
+
 /// A DNS Zone.
 /// Please keep in mind that this zone may not be the one currently active for the domain.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Version {
     #[serde(rename = "uuid_ref")]
     pub uuid: String,
     pub name: String,
     pub active: bool,
+    /// The creation date, as returned by the API (see created)
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// The date of the last modification, as returned by the API (see updated)
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Every other field returned by the API, sorted by name so that a version is always
+    /// serialised the same way (see snapshot)
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl Version {
+    /// The creation date of the version, if the API returned a valid one.
+    pub fn created(&self) -> Option<SystemTime> {
+        parse_api_time(self.created_at.as_ref()?)
+    }
+
+    /// The date of the last modification of the version, if the API returned a valid one.
+    pub fn updated(&self) -> Option<SystemTime> {
+        parse_api_time(self.updated_at.as_ref()?)
+    }
+}
+
+/// Generate a version name unique enough for our needs, like "prefix-1563184682000".
+pub(crate) fn timestamped_name(prefix: &str) -> String {
+    format!(
+        "{}-{}",
+        prefix,
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    )
 }
 
 /// Get the list of all available domains pertaining to this user.
 pub fn query_available_domains<'a>(api_key: &'a str) -> Result<Vec<Domain<'a>>, Error> {
-    let data: Vec<Domain<'a>> = execute_query(
-        &api_key,
-        "/domain/",
-        query_set_type(HTTPOp::GET),
-        parse_json,
-    )?;
+    let data: Vec<Domain<'a>> =
+        execute_query(api_key, "/domain/", query_set_type(HTTPOp::GET), parse_json)?;
     Ok(data
         .into_iter()
         .map(|mut x| {
@@ -109,53 +262,76 @@ pub fn query_available_domains<'a>(api_key: &'a str) -> Result<Vec<Domain<'a>>,
 }
 
 impl<'a> Domain<'a> {
+    /// The origin of the zone, against which relative record names are resolved.
+    pub fn origin(&self) -> DomainName {
+        DomainName::new(&self.name)
+    }
+
+    /// Fail with Error::ExternalDomain if the zone of the domain isn't hosted by online.net, in
+    /// which case it has no versions to read or modify.
+    pub fn ensure_hosted(&self) -> Result<(), Error> {
+        if self.external {
+            return Err(Error::ExternalDomain(self.name.clone()));
+        }
+        Ok(())
+    }
+
     /// Try to extract the longest matching domain from the list of our available domains and the internal part of the name.
     /// e.g. extract_domain("this.is.a.dummy.test.fr.", {Domain("test.fr"), Domain("nope.fr")}) should return
-    /// the domain associated with "test.fr". and the internal path, aka "this.is.a.dummy"
+    /// the domain associated with "test.fr". and the internal path, aka "this.is.a.dummy".
+    /// The internal path is empty if the name is the domain itself.
+    /// See ZoneResolver for the details of the matching.
     pub fn find_and_extract_path(
         full_domain_name: &'a str,
         domains: Vec<Domain<'a>>,
     ) -> Option<(Self, &'a str)> {
-        let mut full_domain_name = full_domain_name;
         // delete a trailing dot if any
-        if full_domain_name.ends_with(".") {
-            full_domain_name = &full_domain_name[0..full_domain_name.len() - 1];
-        }
-        for available_domain in domains {
-            if full_domain_name.ends_with(&available_domain.name) {
-                let max_len = full_domain_name.len() - available_domain.name.len() - 1;
-                return Some((available_domain, &full_domain_name[0..max_len]));
-            }
-        }
-        None
+        let full_domain_name = full_domain_name
+            .strip_suffix('.')
+            .unwrap_or(full_domain_name);
+        let name = DomainName::new(full_domain_name);
+        let (domain, _) = ZoneResolver::new(domains).resolve_owned(&name)?;
+
+        // The resolved name may differ from the one we were given (case, punycode...), but
+        // not by its number of labels, so that's what we rely on to extract the internal path.
+        let internal_labels = name.labels().count() - domain.origin().labels().count();
+        let max_len = if internal_labels == 0 {
+            0
+        } else {
+            full_domain_name
+                .match_indices('.')
+                .nth(internal_labels - 1)
+                .map(|(i, _)| i)?
+        };
+        Some((domain, &full_domain_name[0..max_len]))
     }
 
     /// Extract all records with a name of "entry_name" and with a value of "entry_value" (or any value if entry_value is None) from the zone 'zone'.
+    /// 'entry_name' may be either relative to the domain or absolute.
+    /// See RecordQuery for more elaborate filters.
     pub fn filter_records(
         &self,
         zone: &Version,
         entry_name: &str,
         entry_value: Option<&str>,
     ) -> Result<Option<Vec<Record>>, Error> {
-        let entries = self.get_zone_records(zone)?;
-        let mut res = vec![];
-        for e in entries {
-            if e.name == entry_name {
-                if let Some(data) = entry_value {
-                    if data != e.data {
-                        continue;
-                    }
-                }
-                res.push(e);
-            }
+        let mut query = RecordQuery::new().name(entry_name);
+        if let Some(data) = entry_value {
+            query = query.value(data);
         }
-        if res.len() > 0 {
+        let res = self.query(zone, &query)?;
+        if !res.is_empty() {
             Ok(Some(res))
         } else {
             Ok(None)
         }
     }
 
+    /// Return the records of the zone 'zone' matching 'query'.
+    pub fn query(&self, zone: &Version, query: &RecordQuery) -> Result<Vec<Record>, Error> {
+        query.run(self, zone)
+    }
+
     /// Append a new entry 'record' to the zone 'destination'.
     /// The target zone MUST be inactive.
     pub fn add_record(&self, destination: &Version, record: &Record) -> Result<Record, Error> {
@@ -166,11 +342,13 @@ impl<'a> Domain<'a> {
 
         let dest_zone_url = format!("/domain/{}/version/{}/zone", self.name, dst.uuid);
         let ttl = record.ttl.to_string();
+        let priority = api_priority(record).to_string();
         let record_type = String::from(&record.record_type);
+        let record_name = name::to_ascii(&record.name)?;
         let post_entries = vec![
-            FormData("name", &record.name),
+            FormData("name", &record_name),
             FormData("type", &record_type),
-            FormData("priority", "12"),
+            FormData("priority", &priority),
             FormData("ttl", &ttl),
             FormData("data", &record.data),
         ];
@@ -214,8 +392,32 @@ impl<'a> Domain<'a> {
         version_name: &str,
     ) -> Result<Version, Error> {
         let zone_entries: Vec<Record> = self.get_zone_records(source)?;
+        self.create_version(version_name, &zone_entries, false)
+    }
+
+    /// Create a new version named 'version_name' holding 'records', and enable it if 'enable'
+    /// is set. The new version is deleted if anything fails, so that no half-built version is
+    /// left behind.
+    /// The lock of the domain, if enabled (see with_lock), is held meanwhile.
+    pub fn create_version(
+        &self,
+        version_name: &str,
+        records: &[Record],
+        enable: bool,
+    ) -> Result<Version, Error> {
+        let _lock = self.lock()?;
         let new_zone = self.add_version(version_name)?;
-        self.set_zone_entries(&new_zone, &zone_entries)?;
+        let res = self.set_zone_entries(&new_zone, records).and_then(|_| {
+            if enable {
+                self.enable_version(&new_zone)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = res {
+            let _ = self.delete_version(&new_zone);
+            return Err(e);
+        }
         Ok(new_zone)
     }
 
@@ -229,7 +431,7 @@ impl<'a> Domain<'a> {
             return Err(Error::ActiveZoneForbidden);
         }
 
-        let bind_zone = to_bind(records);
+        let bind_zone = to_bind(records, &self.origin());
 
         let domain_version_url =
             format!("/domain/{}/version/{}/zone_from_bind", self.name, dst.uuid);
@@ -243,8 +445,9 @@ impl<'a> Domain<'a> {
 
     /// Create a new (disabled at the moment) zone.
     pub fn add_version(&self, name: &str) -> Result<Version, Error> {
+        self.ensure_hosted()?;
         let domain_version_url = format!("/domain/{}/version", self.name);
-        let domain_version_post_data = vec![FormData("name", &name)];
+        let domain_version_post_data = vec![FormData("name", name)];
         execute_query(
             self.api_key,
             &domain_version_url,
@@ -255,6 +458,7 @@ impl<'a> Domain<'a> {
 
     /// Enable a specific zone as the current one for the domain.
     pub fn enable_version(&self, v: &Version) -> Result<(), Error> {
+        self.ensure_hosted()?;
         let url = format!("/domain/{}/version/{}/enable", self.name, v.uuid);
         execute_query(
             self.api_key,
@@ -267,6 +471,7 @@ impl<'a> Domain<'a> {
     /// Delete an old zone.
     /// As a result, deleting the current zone will fail.
     pub fn delete_version(&self, v: &Version) -> Result<(), Error> {
+        self.ensure_hosted()?;
         let url = format!("/domain/{}/version/{}", self.name, v.uuid);
         execute_query(
             self.api_key,
@@ -278,26 +483,28 @@ impl<'a> Domain<'a> {
 
     /// Return the version of a given uuid
     pub fn get_version(&self, uuid: &str) -> Result<Version, Error> {
+        self.ensure_hosted()?;
         let url = format!("/domain/{}/version/{}", self.name, uuid);
         execute_query(self.api_key, &url, query_set_type(HTTPOp::GET), parse_json)
     }
 
     /// Return the list of all available zones.
     pub fn get_versions(&self) -> Result<Vec<Version>, Error> {
+        self.ensure_hosted()?;
         let url = format!("/domain/{}/version", self.name);
         execute_query(self.api_key, &url, query_set_type(HTTPOp::GET), parse_json)
     }
 
     /// Retrieve the Version describing the currently enable zone
     pub fn get_current_version(&self) -> Result<Version, Error> {
+        self.ensure_hosted()?;
         let url = format!("/domain/{}/version", self.name);
         let versions: Vec<Version> =
             execute_query(self.api_key, &url, query_set_type(HTTPOp::GET), parse_json)?;
-        Ok(versions
+        versions
             .into_iter()
-            .filter(|x| x.active)
-            .next()
-            .ok_or(Error::InvalidVersion)?)
+            .find(|x| x.active)
+            .ok_or(Error::InvalidVersion)
     }
 
     /// Return the list of all the records in the zone 'zone'.
@@ -311,6 +518,14 @@ impl<'a> Domain<'a> {
         )
     }
 
+    /// Return the changes between the versions 'a' and 'b' of the zone, i.e. what must be done to go
+    /// from 'a' to 'b'.
+    pub fn diff_versions(&self, a: &Version, b: &Version) -> Result<ZoneDiff, Error> {
+        let old = self.get_zone_records(a)?;
+        let new = self.get_zone_records(b)?;
+        Ok(ZoneDiff::compute(&old, &new, &self.origin()))
+    }
+
     /// Update a record in a version of the zone, provided it is not the active one (the APÏ
     /// disallows it).
     pub fn update_version_record(
@@ -326,11 +541,17 @@ impl<'a> Domain<'a> {
 
         let record_type = String::from(&record.record_type);
         let ttl = record.ttl.to_string();
+        let priority = api_priority(&Record {
+            data: new_value.into(),
+            ..record.clone()
+        })
+        .to_string();
+        let record_name = name::to_ascii(&record.name)?;
 
         let patch_entries = vec![
-            FormData("name", &record.name),
+            FormData("name", &record_name),
             FormData("type", &record_type),
-            FormData("priority", "12"),
+            FormData("priority", &priority),
             FormData("ttl", &ttl),
             FormData("data", new_value),
         ];
@@ -343,49 +564,6 @@ impl<'a> Domain<'a> {
         )
     }
 
-    /// Update a record in the current version, by replacing its value.
-    pub fn update_current_version_record(
-        &self,
-        record: &Record,
-        new_value: &str,
-    ) -> Result<(), Error> {
-        self.execute_on_fake_version(|domain, version| {
-            domain.update_version_record(version, &record, new_value)
-        })
-    }
-
-    // Online.net api is buggy, and we cannot directly edit a record in the current zone (as is
-    // expected per the API docs), BUT we can update the zone by lying about the zone
-    // version we are updating: we create a fake version, and we ask the API servers to
-    // update a record in the active zone (specified by its ID), while saying it is in
-    // the new version we just created. This call succeeds and edit the current
-    // version, instead of telling us that this record doesn't exist in the new
-    // version. I love that kind of bugs (but I hope hope it's not as security issue!) ;)
-    pub fn execute_on_fake_version<F, R>(&self, f: F) -> Result<R, Error>
-    where
-        F: Fn(&Domain, &Version) -> Result<R, Error>,
-    {
-        let version_name = format!(
-            "tmp-autoedit-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()
-        );
-
-        let new_version = self.add_version(&version_name)?;
-
-        let res = f(self, &new_version);
-
-        self.delete_version(&new_version)?;
-
-        let version = self.get_current_version()?;
-        // we need to re-enable the current version to tell the dns servers to reload the zone
-        self.enable_version(&version)?;
-
-        res
-    }
-
     /// Retrieve the record identified by its 'record_id' in the version 'version'.
     pub fn get_record(&self, version: &Version, record_id: usize) -> Result<Record, Error> {
         let url = format!(
@@ -411,3 +589,59 @@ impl<'a> Domain<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domains(names: &[&str]) -> Vec<Domain<'static>> {
+        names
+            .iter()
+            .map(|name| Domain {
+                api_key: "key",
+                id: 0,
+                name: (*name).into(),
+                dnssec: false,
+                external: false,
+                lock: None,
+            })
+            .collect()
+    }
+
+    fn extract(name: &'static str, names: &[&str]) -> Option<(String, &'static str)> {
+        Domain::find_and_extract_path(name, domains(names)).map(|(d, path)| (d.name, path))
+    }
+
+    #[test]
+    fn extract_path() {
+        assert_eq!(
+            extract("this.is.a.dummy.test.fr.", &["nope.fr", "test.fr"]),
+            Some(("test.fr".into(), "this.is.a.dummy"))
+        );
+        assert_eq!(extract("foo.notest.fr", &["test.fr"]), None);
+    }
+
+    #[test]
+    fn extract_path_of_the_apex() {
+        assert_eq!(
+            extract("test.fr", &["test.fr"]),
+            Some(("test.fr".into(), ""))
+        );
+        assert_eq!(
+            extract("TEST.fr.", &["test.fr"]),
+            Some(("test.fr".into(), ""))
+        );
+    }
+
+    #[test]
+    fn extract_path_of_an_internationalized_name() {
+        assert_eq!(
+            extract("www.bücher.de", &["xn--bcher-kva.de"]),
+            Some(("xn--bcher-kva.de".into(), "www"))
+        );
+        assert_eq!(
+            extract("bücher.de.", &["xn--bcher-kva.de"]),
+            Some(("xn--bcher-kva.de".into(), ""))
+        );
+    }
+}
@@ -1,18 +1,225 @@
+use std::collections::HashMap;
+
 use crate::api::Record;
+use crate::name::{self, DomainName};
 use crate::net::DNSType;
+use crate::set::{canonical_order, normalise_target, priority_and_fields, txt_content};
+
+/// The TTL used when a zone holds no record to take it from.
+pub const DEFAULT_TTL: usize = 86400;
+
+/// The longest string a TXT record can hold, longer contents are split in several strings.
+const MAX_TXT_STRING: usize = 255;
 
-/// Generate a list of entries in a form similar to bind, like:
-/// "host2.homelab.local.        IN      A       10.1.100.91" 
-pub fn to_bind(records: &[Record]) -> String {
-    let mut res = String::new();
+/// The most common TTL among 'records', used as the default TTL ($TTL) of the zone file.
+pub fn default_ttl(records: &[Record]) -> usize {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
     for r in records {
-        let record_bind = 
-            if r.record_type == DNSType::MX {
-                format!("{} IN MX 10 {}\n", r.name, r.data)
-            } else {
-                format!("{} IN {} {}\n", r.name, &String::from(&r.record_type), r.data)
-            };
-        res.push_str(&record_bind);
+        *counts.entry(r.ttl).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        // on ties, prefer the longest ttl, so that the result doesn't depend on the hash order
+        .max_by_key(|&(ttl, count)| (count, ttl))
+        .map(|(ttl, _)| ttl)
+        .unwrap_or(DEFAULT_TTL)
+}
+
+/// Quote 'content' as a sequence of character strings, escaping the quotes and backslashes.
+pub(crate) fn quote_txt(content: &str) -> String {
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for c in content.chars() {
+        if current_len + c.len_utf8() > MAX_TXT_STRING {
+            strings.push(format!("\"{}\"", current));
+            current.clear();
+            current_len = 0;
+        }
+        if c == '"' || c == '\\' {
+            current.push('\\');
+        }
+        current.push(c);
+        current_len += c.len_utf8();
+    }
+    strings.push(format!("\"{}\"", current));
+    strings.join(" ")
+}
+
+/// Format the data of 'record', which belongs to the zone 'origin', as expected in a zone file.
+/// The names the record points to are written absolute, as the API may return them without
+/// their trailing dot, which would make them relative to $ORIGIN.
+fn format_data(record: &Record, origin: &DomainName) -> String {
+    let fields: Vec<&str> = record.data.split_whitespace().collect();
+    match record.record_type {
+        DNSType::TXT => quote_txt(&txt_content(&record.data)),
+        DNSType::CNAME | DNSType::NS if fields.len() == 1 => normalise_target(fields[0], origin),
+        DNSType::MX | DNSType::SRV if !fields.is_empty() => {
+            let (priority, fields) = priority_and_fields(record);
+            let mut res = vec![priority.to_string()];
+            if let Some((target, rest)) = fields.split_last() {
+                res.extend(rest.iter().map(|f| f.to_string()));
+                res.push(normalise_target(target, origin));
+            }
+            res.join(" ")
+        }
+        DNSType::CAA if fields.len() >= 3 => format!(
+            "{} {} {}",
+            fields[0],
+            fields[1].to_ascii_lowercase(),
+            quote_txt(&txt_content(&fields[2..].join(" ")))
+        ),
+        _ => fields.join(" "),
+    }
+}
+
+/// The owner of 'record' as written in a zone file whose origin is 'origin': "@" for the apex,
+/// relative for the names inside the zone, and absolute (with a trailing dot) otherwise.
+pub(crate) fn format_owner(record: &Record, origin: &DomainName, unicode: bool) -> String {
+    let name = record.domain_name(origin);
+    let owner = match name.relative_to(origin) {
+        Some("") => return "@".into(),
+        Some(relative) => relative.to_string(),
+        None => name.to_fqdn(),
+    };
+    if unicode {
+        name::to_unicode(&owner)
+    } else {
+        owner
+    }
+}
+
+/// How to lay out a zone file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BindStyle {
+    /// Display internationalized names in their Unicode form
+    pub unicode: bool,
+    /// Sort the records in the canonical order (see set::canonical_order)
+    pub canonical: bool,
+    /// Align the owner, ttl, class and type columns
+    pub align: bool,
+}
+
+impl BindStyle {
+    /// The layout giving the same output for the same records, whatever their order, and
+    /// producing readable diffs between versions.
+    pub fn canonical() -> Self {
+        BindStyle {
+            unicode: false,
+            canonical: true,
+            align: true,
+        }
+    }
+}
+
+/// Same as to_bind, laid out according to 'style'.
+pub fn to_bind_with(records: &[Record], origin: &DomainName, style: &BindStyle) -> String {
+    let sorted;
+    let records = if style.canonical {
+        sorted = canonical_order(records, origin);
+        &sorted[..]
+    } else {
+        records
+    };
+
+    let origin_name = if style.unicode {
+        format!("{}.", origin.to_unicode())
+    } else {
+        origin.to_fqdn()
+    };
+    let mut res = format!("$ORIGIN {}\n$TTL {}\n", origin_name, default_ttl(records));
+
+    let lines: Vec<[String; 4]> = records
+        .iter()
+        .map(|r| {
+            [
+                format_owner(r, origin, style.unicode),
+                r.ttl.to_string(),
+                String::from(&r.record_type),
+                format_data(r, origin),
+            ]
+        })
+        .collect();
+    let width = |column: usize| {
+        if style.align {
+            lines
+                .iter()
+                .map(|l| l[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        }
+    };
+    let (owner_width, ttl_width, type_width) = (width(0), width(1), width(2));
+    for [owner, ttl, record_type, data] in &lines {
+        res.push_str(&format!(
+            "{:<owner_width$} {:>ttl_width$} IN {:<type_width$} {}\n",
+            owner,
+            ttl,
+            record_type,
+            data,
+            owner_width = owner_width,
+            ttl_width = ttl_width,
+            type_width = type_width
+        ));
     }
     res
 }
+
+/// Generate a zone file (RFC 1035) holding 'records', whose origin is 'origin', like:
+/// "$ORIGIN homelab.local.
+/// $TTL 3600
+/// host2 3600 IN A 10.1.100.91"
+/// Every record carries its own TTL, the names are relative to the origin when possible, and the
+/// TXT strings are quoted.
+pub fn to_bind(records: &[Record], origin: &DomainName) -> String {
+    to_bind_with(records, origin, &BindStyle::default())
+}
+
+/// Same as to_bind, but with internationalized names displayed in their Unicode form.
+/// This output is meant to be read by humans, don't send it back to the API.
+pub fn to_bind_unicode(records: &[Record], origin: &DomainName) -> String {
+    let style = BindStyle {
+        unicode: true,
+        ..BindStyle::default()
+    };
+    to_bind_with(records, origin, &style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    #[test]
+    fn targets_are_absolute() {
+        let records = vec![
+            Record::new("www", "CNAME", "target.example.com", 3600),
+            Record::new("ftp", "CNAME", "www", 3600),
+            Record::new("@", "NS", "ns1.example.net.", 3600),
+            Record::new("@", "MX", "10 mail", 3600),
+            Record::new("_sip._tcp", "SRV", "10 5 5060 sip.example.com", 3600),
+        ];
+        let zone = to_bind(&records, &origin());
+        assert!(zone.contains("www 3600 IN CNAME target.example.com.\n"));
+        assert!(zone.contains("ftp 3600 IN CNAME www.example.com.\n"));
+        assert!(zone.contains("@ 3600 IN NS ns1.example.net.\n"));
+        assert!(zone.contains("@ 3600 IN MX 10 mail.example.com.\n"));
+        assert!(zone.contains("_sip._tcp 3600 IN SRV 10 5 5060 sip.example.com.\n"));
+    }
+
+    #[test]
+    fn priority_apart_from_the_data() {
+        let mut mx = Record::new("@", "MX", "mail.example.com.", 300);
+        mx.priority = Some(20);
+        let mut srv = Record::new("_sip._tcp", "SRV", "5 5060 sip.example.com.", 300);
+        srv.priority = Some(1);
+        let zone = to_bind(&[mx, srv], &origin());
+        assert!(zone.contains("@ 300 IN MX 20 mail.example.com.\n"));
+        assert!(zone.contains("_sip._tcp 300 IN SRV 1 5 5060 sip.example.com.\n"));
+    }
+}
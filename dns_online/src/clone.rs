@@ -0,0 +1,103 @@
+use crate::api::{timestamped_name, Domain, Record, Version};
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::net::DNSType;
+
+/// Settings for clone_zone.
+#[derive(Clone, Debug)]
+pub struct CloneOptions {
+    version_name: Option<String>,
+    exclude: Vec<DNSType>,
+    enable: bool,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        CloneOptions {
+            version_name: None,
+            exclude: vec![DNSType::NS],
+            enable: false,
+        }
+    }
+}
+
+impl CloneOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the version created on the destination ("clone-<timestamp>" by default).
+    pub fn version_name(mut self, name: &str) -> Self {
+        self.version_name = Some(name.into());
+        self
+    }
+
+    /// Don't copy the records of these types (only NS by default, as the name servers of a
+    /// domain are rarely the ones of another).
+    pub fn exclude(mut self, record_types: &[DNSType]) -> Self {
+        self.exclude = record_types.to_vec();
+        self
+    }
+
+    /// Enable the version created on the destination (false by default).
+    pub fn enable(mut self, enable: bool) -> Self {
+        self.enable = enable;
+        self
+    }
+}
+
+/// Rewrite the name 'target' found in the data of a record, if it points inside 'from'.
+fn rewrite_target(target: &str, from: &DomainName, to: &DomainName) -> String {
+    match DomainName::from_relative(target, from).rebase(from, to) {
+        Some(name) => name.to_fqdn(),
+        None => target.into(),
+    }
+}
+
+/// Move 'record' from the zone 'from' to the zone 'to': its name, along with the names it
+/// points to (for CNAME, NS, MX and SRV records), are rewritten when they are inside 'from'.
+pub fn rewrite_record(record: &Record, from: &DomainName, to: &DomainName) -> Record {
+    let mut res = record.clone();
+    if let Some(name) = record.domain_name(from).rebase(from, to) {
+        res.name = name.to_fqdn();
+    }
+
+    let mut fields: Vec<&str> = record.data.split_whitespace().collect();
+    let target_field = match record.record_type {
+        DNSType::CNAME | DNSType::NS if fields.len() == 1 => Some(0),
+        // The preference may or may not be part of the data
+        DNSType::MX if fields.len() == 1 || fields.len() == 2 => Some(fields.len() - 1),
+        DNSType::SRV if fields.len() == 3 || fields.len() == 4 => Some(fields.len() - 1),
+        _ => None,
+    };
+    if let Some(i) = target_field {
+        let target = rewrite_target(fields[i], from, to);
+        fields[i] = &target;
+        res.data = fields.join(" ");
+    }
+    res
+}
+
+/// Copy the records of the version 'version' of 'from' into a new version of 'to', rewriting
+/// the names from one domain to the other (see rewrite_record). Return the new version.
+pub fn clone_zone(
+    from: &Domain,
+    version: &Version,
+    to: &Domain,
+    options: &CloneOptions,
+) -> Result<Version, Error> {
+    let (from_origin, to_origin) = (from.origin(), to.origin());
+    let records: Vec<Record> = from
+        .get_zone_records(version)?
+        .iter()
+        .filter(|r| !options.exclude.contains(&r.record_type))
+        .map(|r| rewrite_record(r, &from_origin, &to_origin))
+        .collect();
+
+    let version_name = options
+        .version_name
+        .clone()
+        .unwrap_or_else(|| timestamped_name("clone"));
+
+    to.create_version(&version_name, &records, options.enable)
+}
@@ -0,0 +1,256 @@
+use serde_derive::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::api::Record;
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::net::DNSType;
+use crate::set::{self, RecordKey};
+
+/// A record present in both sides of a diff, but with a different value or ttl.
+#[derive(Serialize, Clone, Debug)]
+pub struct RecordChange {
+    pub old: Record,
+    pub new: Record,
+}
+
+/// The differences between two sets of records (typically two versions of a zone).
+/// Records are compared by identity (see RecordKey): a record whose value changed shows up in
+/// 'modified' when it is the only one of its name and type on both sides (the usual case for
+/// a record being updated), and as a removal plus an addition otherwise.
+#[derive(Serialize, Clone, Debug)]
+pub struct ZoneDiff {
+    #[serde(skip)]
+    origin: DomainName,
+    pub added: Vec<Record>,
+    pub removed: Vec<Record>,
+    pub modified: Vec<RecordChange>,
+    /// Records whose ttl is the only thing that changed
+    pub ttl_changed: Vec<RecordChange>,
+}
+
+impl ZoneDiff {
+    /// Compute the changes needed to go from 'old' to 'new', both belonging to the zone
+    /// 'origin'.
+    pub fn compute(old: &[Record], new: &[Record], origin: &DomainName) -> ZoneDiff {
+        let old = set::dedup(old, origin);
+        let new = set::dedup(new, origin);
+        let old_keys: HashMap<RecordKey, &Record> =
+            old.iter().map(|r| (r.key(origin), r)).collect();
+
+        let mut ttl_changed = Vec::new();
+        for r in &new {
+            if let Some(old_record) = old_keys.get(&r.key(origin)) {
+                if old_record.ttl != r.ttl {
+                    ttl_changed.push(RecordChange {
+                        old: (*old_record).clone(),
+                        new: r.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut removed = set::difference(&old, &new, origin);
+        let mut added = set::difference(&new, &old, origin);
+
+        // pair the lone removals and additions sharing the same name and type
+        let rrset = |r: &Record| (r.domain_name(origin), r.record_type);
+        let mut counts: HashMap<(DomainName, DNSType), (usize, usize)> = HashMap::new();
+        for r in &removed {
+            counts.entry(rrset(r)).or_default().0 += 1;
+        }
+        for r in &added {
+            counts.entry(rrset(r)).or_default().1 += 1;
+        }
+        let is_pair = |r: &Record| counts.get(&rrset(r)) == Some(&(1, 1));
+        let mut modified: Vec<RecordChange> = removed
+            .iter()
+            .filter(|r| is_pair(r))
+            .map(|old_record| RecordChange {
+                old: old_record.clone(),
+                new: added
+                    .iter()
+                    .find(|r| rrset(r) == rrset(old_record))
+                    .cloned()
+                    .unwrap(),
+            })
+            .collect();
+        removed.retain(|r| !is_pair(r));
+        added.retain(|r| !is_pair(r));
+
+        added.sort_by_key(|r| r.key(origin));
+        removed.sort_by_key(|r| r.key(origin));
+        modified.sort_by_key(|c| c.old.key(origin));
+        ttl_changed.sort_by_key(|c| c.old.key(origin));
+
+        ZoneDiff {
+            origin: origin.clone(),
+            added,
+            removed,
+            modified,
+            ttl_changed,
+        }
+    }
+
+    /// Return true if both sides hold the same records, with the same ttls.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.ttl_changed.is_empty()
+    }
+
+    /// Render the diff as JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    fn fqdn(&self, r: &Record) -> String {
+        r.domain_name(&self.origin).to_fqdn()
+    }
+}
+
+/// Render the diff as text, one line per change:
+/// "+" for additions, "-" for removals, "~" for modifications.
+impl fmt::Display for ZoneDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in &self.removed {
+            writeln!(
+                f,
+                "- {} {} IN {} {}",
+                self.fqdn(r),
+                r.ttl,
+                String::from(&r.record_type),
+                r.data
+            )?;
+        }
+        for r in &self.added {
+            writeln!(
+                f,
+                "+ {} {} IN {} {}",
+                self.fqdn(r),
+                r.ttl,
+                String::from(&r.record_type),
+                r.data
+            )?;
+        }
+        for c in &self.modified {
+            writeln!(
+                f,
+                "~ {} IN {} {} -> {}",
+                self.fqdn(&c.old),
+                String::from(&c.old.record_type),
+                c.old.data,
+                c.new.data
+            )?;
+            if c.old.ttl != c.new.ttl {
+                writeln!(f, "  (ttl {} -> {})", c.old.ttl, c.new.ttl)?;
+            }
+        }
+        for c in &self.ttl_changed {
+            writeln!(
+                f,
+                "~ {} IN {} {} (ttl {} -> {})",
+                self.fqdn(&c.old),
+                String::from(&c.old.record_type),
+                c.old.data,
+                c.old.ttl,
+                c.new.ttl
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZoneDiff;
+    use crate::api::Record;
+    use crate::name::DomainName;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    #[test]
+    fn identical_zones() {
+        let old = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("@", "MX", "10 mail.example.com.", 3600),
+        ];
+        // same records, named differently and in another order
+        let new = vec![
+            Record::new("example.com.", "MX", "10 mail", 3600),
+            Record::new("www.example.com.", "A", "192.0.2.1", 3600),
+        ];
+        assert!(ZoneDiff::compute(&old, &new, &origin()).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let old = vec![
+            Record::new("a", "A", "192.0.2.1", 3600),
+            Record::new("b", "TXT", "\"hello\"", 3600),
+        ];
+        let new = vec![
+            Record::new("a", "A", "192.0.2.1", 3600),
+            Record::new("c", "AAAA", "2001:db8::1", 3600),
+        ];
+        let diff = ZoneDiff::compute(&old, &new, &origin());
+        assert_eq!(diff.removed, vec![old[1].clone()]);
+        assert_eq!(diff.added, vec![new[1].clone()]);
+        assert!(diff.modified.is_empty());
+        assert!(diff.ttl_changed.is_empty());
+    }
+
+    #[test]
+    fn lone_changes_are_modifications() {
+        let old = vec![Record::new("www", "A", "192.0.2.1", 3600)];
+        let new = vec![Record::new("www", "A", "192.0.2.2", 300)];
+        let diff = ZoneDiff::compute(&old, &new, &origin());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].old, old[0]);
+        assert_eq!(diff.modified[0].new, new[0]);
+    }
+
+    #[test]
+    fn changes_in_larger_sets_are_not_paired() {
+        let old = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("www", "A", "192.0.2.2", 3600),
+        ];
+        let new = vec![
+            Record::new("www", "A", "192.0.2.3", 3600),
+            Record::new("www", "A", "192.0.2.4", 3600),
+        ];
+        let diff = ZoneDiff::compute(&old, &new, &origin());
+        assert!(diff.modified.is_empty());
+        assert_eq!(diff.removed.len(), 2);
+        assert_eq!(diff.added.len(), 2);
+    }
+
+    #[test]
+    fn ttl_changes() {
+        let old = vec![Record::new("www", "A", "192.0.2.1", 3600)];
+        let new = vec![Record::new("www", "A", "192.0.2.1", 300)];
+        let diff = ZoneDiff::compute(&old, &new, &origin());
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty());
+        assert_eq!(diff.ttl_changed.len(), 1);
+        assert_eq!(
+            (diff.ttl_changed[0].old.ttl, diff.ttl_changed[0].new.ttl),
+            (3600, 300)
+        );
+    }
+
+    #[test]
+    fn duplicates_are_ignored() {
+        let old = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("www", "A", "192.0.2.1", 3600),
+        ];
+        let new = vec![Record::new("www", "A", "192.0.2.1", 3600)];
+        assert!(ZoneDiff::compute(&old, &new, &origin()).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use serde_derive::*;
+use std::collections::BTreeMap;
+
+use crate::api::Domain;
+use crate::error::Error;
+use crate::net::*;
+
+/// A key signing the zone of a domain, as returned by the API, with the material to publish at
+/// the registrar.
+/// The API doesn't return every field for every key, hence the Options.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DnssecKey {
+    #[serde(default, alias = "keytag")]
+    pub key_tag: Option<u32>,
+    #[serde(default)]
+    pub flags: Option<u16>,
+    #[serde(default)]
+    pub algorithm: Option<u8>,
+    /// The public key, in base64
+    #[serde(default, alias = "dnskey")]
+    pub public_key: Option<String>,
+    #[serde(default)]
+    pub digest_type: Option<u8>,
+    /// The digest of the key, in hexadecimal
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// Every other field returned by the API, sorted by name
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl DnssecKey {
+    /// The DS record to publish at the registrar for the domain 'domain_name', if the API
+    /// returned everything needed to build it.
+    pub fn ds_record(&self, domain_name: &str) -> Option<String> {
+        Some(format!(
+            "{}. IN DS {} {} {} {}",
+            domain_name.trim_end_matches('.'),
+            self.key_tag?,
+            self.algorithm?,
+            self.digest_type?,
+            self.digest.as_ref()?.to_uppercase()
+        ))
+    }
+
+    /// The DNSKEY record of this key for the domain 'domain_name', if the API returned
+    /// everything needed to build it.
+    pub fn dnskey_record(&self, domain_name: &str) -> Option<String> {
+        Some(format!(
+            "{}. IN DNSKEY {} 3 {} {}",
+            domain_name.trim_end_matches('.'),
+            self.flags?,
+            self.algorithm?,
+            self.public_key.as_ref()?
+        ))
+    }
+}
+
+impl<'a> Domain<'a> {
+    /// Query the API for the current DNSSEC status of the domain. The 'dnssec' field is only
+    /// as fresh as the list of domains it was obtained from.
+    pub fn dnssec_enabled(&self) -> Result<bool, Error> {
+        let url = format!("/domain/{}", self.name);
+        let domain: Domain =
+            execute_query(self.api_key, &url, query_set_type(HTTPOp::GET), parse_json)?;
+        Ok(domain.dnssec)
+    }
+
+    fn set_dnssec(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ensure_hosted()?;
+        let _lock = self.lock()?;
+        let url = format!("/domain/{}", self.name);
+        let value = if enabled { "true" } else { "false" };
+        execute_query(
+            self.api_key,
+            &url,
+            query_set_type(HTTPOp::PATCH(Some(&[FormData("dnssec", value)]))),
+            throw_value,
+        )?;
+        self.dnssec = enabled;
+        Ok(())
+    }
+
+    /// Sign the zone of the domain. Don't forget to publish the DS records (see dnssec_keys) at
+    /// the registrar afterwards.
+    pub fn enable_dnssec(&mut self) -> Result<(), Error> {
+        self.set_dnssec(true)
+    }
+
+    /// Stop signing the zone of the domain. The DS records must be removed from the registrar
+    /// BEFORE doing so, or resolvers will consider the domain as bogus.
+    pub fn disable_dnssec(&mut self) -> Result<(), Error> {
+        self.set_dnssec(false)
+    }
+
+    /// Retrieve the keys signing the zone of the domain, with their DS material. The list is
+    /// empty when DNSSEC is disabled.
+    pub fn dnssec_keys(&self) -> Result<Vec<DnssecKey>, Error> {
+        if !self.dnssec {
+            return Ok(Vec::new());
+        }
+        let url = format!("/domain/{}/dnssec", self.name);
+        execute_query(self.api_key, &url, query_set_type(HTTPOp::GET), parse_json)
+    }
+}
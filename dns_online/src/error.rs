@@ -1,5 +1,8 @@
 use std::convert;
 use std::fmt::{Debug, Formatter, Result};
+use std::path::PathBuf;
+
+use crate::diff::ZoneDiff;
 
 /// Returned when a request can't be completed and isn't expected, this may allow you to determine
 /// why this "exception" was thrown
@@ -9,6 +12,27 @@ pub struct APIError {
     pub status_code: u32,
 }
 
+/// Returned when the active zone doesn't hold what we expected after enabling a version
+pub struct VerificationError {
+    /// The uuid of the version we enabled
+    pub expected_version: String,
+    /// The uuid of the version actually active after enabling it
+    pub active_version: String,
+    /// The changes between the expected records and those of the active zone
+    pub diff: ZoneDiff,
+    /// Whether the previously active version could be enabled again
+    pub rolled_back: bool,
+}
+
+/// Returned when a zone file cannot be parsed, with the position (1-based) of the culprit
+pub struct ParseError {
+    /// The file being parsed, if the zone wasn't given as a string
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
 /// The various errors thay may be returned by the API, ranging from network errors to unproper use
 /// of the API, and through serializations errors.
 pub enum Error {
@@ -16,6 +40,12 @@ pub enum Error {
     CurlError(curl::Error),
     /// JSON parsing error
     SerdeError(serde_json::Error),
+    /// YAML parsing error
+    YamlError(serde_yaml::Error),
+    /// CSV parsing error
+    CsvError(csv::Error),
+    /// The content doesn't follow the expected format (the reason is given)
+    InvalidFormat(String),
     /// The remote endpoint returned a HTTP error code
     ApiError(APIError),
     /// A conversion to an utf-8 string failed
@@ -28,6 +58,38 @@ pub enum Error {
     InvalidPost,
     /// No matching record found
     NoRecord,
+    /// The domain name cannot be converted to a valid (ASCII) name
+    InvalidName(String),
+    /// A regular expression supplied by the user is invalid
+    RegexError(regex::Error),
+    /// The active zone doesn't match what was expected after a modification
+    VerificationFailed(Box<VerificationError>),
+    /// A file operation failed
+    IoError(std::io::Error),
+    /// The lock of the domain (whose lock file path is given) is still held by someone else
+    LockTimeout(PathBuf),
+    /// The lock directory (whose path is given) could be tampered with by other users, who
+    /// could then hold our locks forever: it must belong to us or to root, and must not be
+    /// writable by everyone
+    InsecureLockDirectory(PathBuf),
+    /// The zone of the domain (whose name is given) is hosted outside of online.net, so it can't
+    /// be managed through the API
+    ExternalDomain(String),
+    /// The domain (whose name is given) is present in several of the accounts used, so we can't
+    /// tell which one to use
+    DuplicateDomain(String),
+    /// A zone file is invalid
+    ZoneParseError(ParseError),
+    /// The change could not be applied in place: only deletions and updates can, and only as
+    /// long as the API lets us (see Domain::modify_active)
+    InPlaceUnsupported,
+    /// Another version was enabled while we were preparing ours
+    ConcurrentModification {
+        /// The uuid of the version our changes are based on
+        base: String,
+        /// The uuid of the version active when we tried to enable ours
+        active: String,
+    },
 }
 
 impl convert::From<curl::Error> for Error {
@@ -48,6 +110,30 @@ impl convert::From<std::string::FromUtf8Error> for Error {
     }
 }
 
+impl convert::From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Error {
+        Error::RegexError(e)
+    }
+}
+
+impl convert::From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Error {
+        Error::YamlError(e)
+    }
+}
+
+impl convert::From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Error {
+        Error::CsvError(e)
+    }
+}
+
+impl convert::From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::IoError(e)
+    }
+}
+
 impl Debug for Error {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match self {
@@ -57,6 +143,15 @@ impl Debug for Error {
             Error::SerdeError(e) => {
                 write!(f, "Parsing Error({:?})", e)?;
             }
+            Error::YamlError(e) => {
+                write!(f, "YAML Parsing Error({:?})", e)?;
+            }
+            Error::CsvError(e) => {
+                write!(f, "CSV Parsing Error({:?})", e)?;
+            }
+            Error::InvalidFormat(reason) => {
+                write!(f, "Invalid Format({})", reason)?;
+            }
             Error::FromUtf8Error(e) => {
                 write!(f, "UTF8 Conversion Error({:?})", e)?;
             }
@@ -69,7 +164,7 @@ impl Debug for Error {
                         &body_str[body_str.len() - 50..body_str.len()]
                     )
                 } else {
-                    format!("{}", body_str)
+                    body_str
                 };
                 write!(
                     f,
@@ -89,6 +184,63 @@ impl Debug for Error {
             Error::NoRecord => {
                 write!(f, "Couldn't find a matching record")?;
             }
+            Error::InvalidName(name) => {
+                write!(f, "Invalid Domain Name '{}'", name)?;
+            }
+            Error::RegexError(e) => {
+                write!(f, "Regex Error({:?})", e)?;
+            }
+            Error::IoError(e) => {
+                write!(f, "IO Error({:?})", e)?;
+            }
+            Error::LockTimeout(path) => {
+                write!(f, "Timeout while waiting for the lock '{}'", path.display())?;
+            }
+            Error::InsecureLockDirectory(path) => {
+                write!(
+                    f,
+                    "The lock directory '{}' is writable by other users",
+                    path.display()
+                )?;
+            }
+            Error::ExternalDomain(name) => {
+                write!(
+                    f,
+                    "The domain '{}' is hosted outside of online.net, its zone can't be managed",
+                    name
+                )?;
+            }
+            Error::DuplicateDomain(name) => {
+                write!(f, "The domain '{}' is present in several accounts", name)?;
+            }
+            Error::ZoneParseError(e) => {
+                let file = match &e.file {
+                    Some(file) => format!("{}:", file.display()),
+                    None => String::new(),
+                };
+                write!(
+                    f,
+                    "Zone File Error({}{}:{}: {})",
+                    file, e.line, e.column, e.message
+                )?;
+            }
+            Error::InPlaceUnsupported => {
+                write!(f, "The change cannot be applied in place")?;
+            }
+            Error::ConcurrentModification { base, active } => {
+                write!(
+                    f,
+                    "Concurrent Modification(base_version = '{}', active_version = '{}')",
+                    base, active
+                )?;
+            }
+            Error::VerificationFailed(e) => {
+                write!(
+                    f,
+                    "Verification Error(expected_version = '{}', active_version = '{}', rolled_back = '{}', diff = '{}')",
+                    e.expected_version, e.active_version, e.rolled_back, e.diff
+                )?;
+            }
         }
         Ok(())
     }
@@ -0,0 +1,451 @@
+use serde_derive::*;
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::api::Record;
+use crate::bind::{format_owner, quote_txt, to_bind, to_bind_with, BindStyle};
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::net::DNSType;
+use crate::set::{canonical_order, normalise_target, priority_and_fields, txt_content};
+use crate::zonefile::{self, parse_type};
+
+/// The names accepted by by_name.
+pub const FORMATS: &[&str] = &["bind", "json", "yaml", "csv", "octodns"];
+
+/// The ttl octoDNS assumes when a record set doesn't specify one.
+const OCTODNS_DEFAULT_TTL: usize = 3600;
+
+/// A textual representation of the records of a zone.
+pub trait ZoneFormat {
+    /// Serialise 'records', which belong to the zone 'origin'.
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error>;
+
+    /// Same as write, with the records in the canonical order (see set::canonical_order), so
+    /// that the same records always give the same output, whatever the order the API returned
+    /// them in.
+    fn write_canonical(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        self.write(&canonical_order(records, origin), origin)
+    }
+
+    /// Parse 'text' into the records of the zone 'origin'.
+    fn read(&self, text: &str, origin: &DomainName) -> Result<Vec<Record>, Error>;
+
+    /// Same as read, with the content of the file at 'path'.
+    fn read_file(&self, path: &Path, origin: &DomainName) -> Result<Vec<Record>, Error> {
+        self.read(&fs::read_to_string(path)?, origin)
+    }
+}
+
+/// Return the format named 'name' (see FORMATS).
+pub fn by_name(name: &str) -> Option<Box<dyn ZoneFormat>> {
+    Some(match name {
+        "bind" => Box::new(Bind),
+        "json" => Box::new(Json),
+        "yaml" => Box::new(Yaml),
+        "csv" => Box::new(Csv),
+        "octodns" => Box::new(OctoDns),
+        _ => return None,
+    })
+}
+
+/// A zone file, see bind::to_bind and zonefile::parse.
+pub struct Bind;
+
+impl ZoneFormat for Bind {
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        Ok(to_bind(records, origin))
+    }
+
+    /// The records are also aligned in columns.
+    fn write_canonical(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        Ok(to_bind_with(records, origin, &BindStyle::canonical()))
+    }
+
+    fn read(&self, text: &str, origin: &DomainName) -> Result<Vec<Record>, Error> {
+        zonefile::parse(text, origin)
+    }
+
+    fn read_file(&self, path: &Path, origin: &DomainName) -> Result<Vec<Record>, Error> {
+        zonefile::parse_file(path, origin)
+    }
+}
+
+/// A record as stored in the JSON, YAML and CSV formats: unlike Record, it doesn't hold the id
+/// (which is meaningless outside of a version), and its name is relative to the zone ("@" for
+/// the apex).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Entry {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: DNSType,
+    ttl: usize,
+    data: String,
+    #[serde(default)]
+    priority: Option<usize>,
+}
+
+impl Entry {
+    fn new(record: &Record, origin: &DomainName) -> Self {
+        Entry {
+            name: format_owner(record, origin, false),
+            record_type: record.record_type,
+            ttl: record.ttl,
+            data: record.data.clone(),
+            priority: record.priority,
+        }
+    }
+
+    fn into_record(self) -> Record {
+        let mut record = Record::new(self.name, self.record_type, self.data, self.ttl);
+        record.priority = self.priority;
+        record
+    }
+}
+
+fn entries(records: &[Record], origin: &DomainName) -> Vec<Entry> {
+    records.iter().map(|r| Entry::new(r, origin)).collect()
+}
+
+/// A JSON array of records.
+pub struct Json;
+
+impl ZoneFormat for Json {
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        let mut res = serde_json::to_string_pretty(&entries(records, origin))?;
+        res.push('\n');
+        Ok(res)
+    }
+
+    fn read(&self, text: &str, _origin: &DomainName) -> Result<Vec<Record>, Error> {
+        let entries: Vec<Entry> = serde_json::from_str(text)?;
+        Ok(entries.into_iter().map(Entry::into_record).collect())
+    }
+}
+
+/// A YAML sequence of records.
+pub struct Yaml;
+
+impl ZoneFormat for Yaml {
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        Ok(serde_yaml::to_string(&entries(records, origin))?)
+    }
+
+    fn read(&self, text: &str, _origin: &DomainName) -> Result<Vec<Record>, Error> {
+        let entries: Vec<Entry> = serde_yaml::from_str(text)?;
+        Ok(entries.into_iter().map(Entry::into_record).collect())
+    }
+}
+
+/// A CSV table of records, with a header line ("name,type,ttl,data,priority").
+pub struct Csv;
+
+impl ZoneFormat for Csv {
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for entry in entries(records, origin) {
+            writer.serialize(entry)?;
+        }
+        let data = writer
+            .into_inner()
+            .map_err(|e| Error::IoError(e.into_error()))?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    fn read(&self, text: &str, _origin: &DomainName) -> Result<Vec<Record>, Error> {
+        let mut reader = csv::Reader::from_reader(text.as_bytes());
+        let mut records = Vec::new();
+        for entry in reader.deserialize() {
+            let entry: Entry = entry?;
+            records.push(entry.into_record());
+        }
+        Ok(records)
+    }
+}
+
+/// The YAML format of octoDNS: records are grouped in sets by name ("" for the apex) and type,
+/// and the values of MX, SRV and CAA records are structured.
+/// octoDNS has a single ttl per set, so writing a set whose records have different ttls fails
+/// with Error::InvalidFormat.
+pub struct OctoDns;
+
+/// The type, ttl and values of a set of octoDNS records.
+type OctoDnsSet = (DNSType, usize, Vec<Value>);
+
+/// The octoDNS value of 'record', with its targets made absolute (octoDNS requires it).
+fn octodns_value(record: &Record, origin: &DomainName) -> Value {
+    let mut value = Mapping::new();
+    match record.record_type {
+        DNSType::TXT => {
+            return Value::String(txt_content(&record.data).replace(';', "\\;"));
+        }
+        DNSType::MX => {
+            let (priority, fields) = priority_and_fields(record);
+            value.insert("preference".into(), priority.into());
+            let exchange = normalise_target(&fields.join(" "), origin);
+            value.insert("exchange".into(), exchange.into());
+        }
+        DNSType::SRV => {
+            let (priority, fields) = priority_and_fields(record);
+            value.insert("priority".into(), priority.into());
+            for (key, field) in ["weight", "port"].iter().zip(&fields) {
+                let field: Value = match field.parse::<u64>() {
+                    Ok(n) => n.into(),
+                    Err(_) => (*field).into(),
+                };
+                value.insert((*key).into(), field);
+            }
+            let target = normalise_target(fields.get(2).copied().unwrap_or(""), origin);
+            value.insert("target".into(), target.into());
+        }
+        DNSType::CAA => {
+            let fields: Vec<&str> = record.data.split_whitespace().collect();
+            if fields.len() < 3 {
+                return Value::String(record.data.clone());
+            }
+            let flags: Value = match fields[0].parse::<u64>() {
+                Ok(n) => n.into(),
+                Err(_) => fields[0].into(),
+            };
+            value.insert("flags".into(), flags);
+            value.insert("tag".into(), fields[1].into());
+            value.insert("value".into(), txt_content(&fields[2..].join(" ")).into());
+        }
+        DNSType::CNAME | DNSType::NS => {
+            return Value::String(normalise_target(record.data.trim(), origin));
+        }
+        _ => return Value::String(record.data.trim().into()),
+    }
+    Value::Mapping(value)
+}
+
+impl ZoneFormat for OctoDns {
+    fn write(&self, records: &[Record], origin: &DomainName) -> Result<String, Error> {
+        // the sets, by name, in the order the names first appear
+        let mut names: Vec<(String, Vec<OctoDnsSet>)> = Vec::new();
+        for r in records {
+            let name = match format_owner(r, origin, false).as_str() {
+                "@" => String::new(),
+                name if name.ends_with('.') => {
+                    return Err(Error::InvalidFormat(format!(
+                        "'{}' is outside of the zone {}",
+                        name, origin
+                    )))
+                }
+                name => name.to_string(),
+            };
+            let sets = match names.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, sets)) => sets,
+                None => {
+                    names.push((name, Vec::new()));
+                    &mut names.last_mut().unwrap().1
+                }
+            };
+            match sets.iter_mut().find(|(t, _, _)| *t == r.record_type) {
+                Some((_, ttl, _)) if *ttl != r.ttl => {
+                    return Err(Error::InvalidFormat(format!(
+                        "the {} records of '{}' have different ttls ({} and {}), which octoDNS \
+                         doesn't support",
+                        String::from(&r.record_type),
+                        r.domain_name(origin),
+                        ttl,
+                        r.ttl
+                    )))
+                }
+                Some((_, _, values)) => values.push(octodns_value(r, origin)),
+                None => sets.push((r.record_type, r.ttl, vec![octodns_value(r, origin)])),
+            }
+        }
+
+        let mut zone = Mapping::new();
+        for (name, sets) in names {
+            let mut sets: Vec<Value> = sets
+                .into_iter()
+                .map(|(record_type, ttl, mut values)| {
+                    let mut set = Mapping::new();
+                    set.insert("type".into(), String::from(&record_type).into());
+                    set.insert("ttl".into(), (ttl as u64).into());
+                    if values.len() == 1 {
+                        set.insert("value".into(), values.pop().unwrap());
+                    } else {
+                        set.insert("values".into(), Value::Sequence(values));
+                    }
+                    Value::Mapping(set)
+                })
+                .collect();
+            let sets = if sets.len() == 1 {
+                sets.pop().unwrap()
+            } else {
+                Value::Sequence(sets)
+            };
+            zone.insert(name.into(), sets);
+        }
+        Ok(serde_yaml::to_string(&zone)?)
+    }
+
+    fn read(&self, text: &str, _origin: &DomainName) -> Result<Vec<Record>, Error> {
+        let zone: Mapping = serde_yaml::from_str(text)?;
+        let mut records = Vec::new();
+        for (name, sets) in zone {
+            let name = match name.as_str() {
+                Some("") => "@".to_string(),
+                Some(name) => name.to_string(),
+                None => return Err(Error::InvalidFormat(format!("invalid name {:?}", name))),
+            };
+            let sets = match sets {
+                Value::Sequence(sets) => sets,
+                set => vec![set],
+            };
+            for set in sets {
+                read_octodns_set(&name, &set, &mut records)?;
+            }
+        }
+        Ok(records)
+    }
+}
+
+fn read_octodns_set(name: &str, set: &Value, records: &mut Vec<Record>) -> Result<(), Error> {
+    let invalid =
+        |reason: &str| Error::InvalidFormat(format!("{} in the records of '{}'", reason, name));
+
+    let record_type = match set.get("type").and_then(Value::as_str) {
+        Some(t) => parse_type(t).ok_or_else(|| invalid(&format!("unsupported type '{}'", t)))?,
+        None => return Err(invalid("missing type")),
+    };
+    let ttl = match set.get("ttl") {
+        Some(ttl) => ttl.as_u64().ok_or_else(|| invalid("invalid ttl"))? as usize,
+        None => OCTODNS_DEFAULT_TTL,
+    };
+    let values = match (set.get("value"), set.get("values")) {
+        (Some(value), None) => vec![value.clone()],
+        (None, Some(Value::Sequence(values))) => values.clone(),
+        _ => return Err(invalid("missing value")),
+    };
+
+    // the fields of structured values may be numbers or strings
+    let field = |value: &Value, key: &str| -> Result<String, Error> {
+        match value.get(key) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(Value::Number(n)) => Ok(n.to_string()),
+            _ => Err(invalid(&format!("missing {}", key))),
+        }
+    };
+    for value in values {
+        let mut priority = None;
+        let data = match record_type {
+            DNSType::MX => {
+                let preference =
+                    field(&value, "preference").or_else(|_| field(&value, "priority"))?;
+                priority = Some(
+                    preference
+                        .parse()
+                        .map_err(|_| invalid("invalid preference"))?,
+                );
+                field(&value, "exchange").or_else(|_| field(&value, "value"))?
+            }
+            DNSType::SRV => {
+                priority = Some(
+                    field(&value, "priority")?
+                        .parse()
+                        .map_err(|_| invalid("invalid priority"))?,
+                );
+                format!(
+                    "{} {} {}",
+                    field(&value, "weight")?,
+                    field(&value, "port")?,
+                    field(&value, "target")?
+                )
+            }
+            DNSType::CAA => format!(
+                "{} {} \"{}\"",
+                field(&value, "flags").unwrap_or_else(|_| "0".into()),
+                field(&value, "tag")?,
+                field(&value, "value")?
+            ),
+            DNSType::TXT => match value.as_str() {
+                // quoted, the way the API returns them
+                Some(txt) => quote_txt(&txt.replace("\\;", ";")),
+                None => return Err(invalid("invalid value")),
+            },
+            _ => match value.as_str() {
+                Some(data) => data.to_string(),
+                None => return Err(invalid("invalid value")),
+            },
+        };
+        let mut record = Record::new(name, record_type, data, ttl);
+        record.priority = priority;
+        records.push(record);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    #[test]
+    fn round_trip() {
+        let records = vec![
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("@", "A", "192.0.2.2", 3600),
+            Record::new("www", "CNAME", "example.com.", 300),
+            Record::new("ftp", "CNAME", "www", 300),
+            Record::new("backup", "MX", "20 mail", 3600),
+            Record::new("@", "MX", "10 mail.example.com.", 3600),
+            Record::new("@", "TXT", "\"v=spf1 -all\"", 3600),
+            Record::new("_sip._tcp", "SRV", "10 5 5060 sip.example.com.", 60),
+        ];
+        for name in FORMATS {
+            let format = by_name(name).unwrap();
+            let text = format.write(&records, &origin()).unwrap();
+            let read = format.read(&text, &origin()).unwrap();
+            assert_eq!(read.len(), records.len(), "{}: {}", name, text);
+            for r in &records {
+                assert!(
+                    read.iter()
+                        .any(|x| x.same_as(r, &origin()) && x.ttl == r.ttl),
+                    "{}: {:?} is missing from {}",
+                    name,
+                    r,
+                    text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn octodns_absolute_targets_and_quoted_txt() {
+        let mut srv = Record::new("_sip._tcp", "SRV", "5 5060 sip", 60);
+        srv.priority = Some(10);
+        let records = vec![
+            Record::new("ftp", "CNAME", "www", 300),
+            Record::new("@", "MX", "10 mail", 3600),
+            srv,
+            Record::new("@", "TXT", "\"v=spf1 -all\"", 3600),
+        ];
+        let text = OctoDns.write(&records, &origin()).unwrap();
+        assert!(text.contains("value: www.example.com."), "{}", text);
+        assert!(text.contains("exchange: mail.example.com."), "{}", text);
+        assert!(text.contains("target: sip.example.com."), "{}", text);
+        let read = OctoDns.read(&text, &origin()).unwrap();
+        let txt = read.iter().find(|r| r.record_type == DNSType::TXT).unwrap();
+        assert_eq!(txt.data, "\"v=spf1 -all\"");
+    }
+
+    #[test]
+    fn octodns_rejects_different_ttls() {
+        let records = vec![
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("@", "A", "192.0.2.2", 300),
+        ];
+        assert!(matches!(
+            OctoDns.write(&records, &origin()),
+            Err(Error::InvalidFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use crate::api::{timestamped_name, Domain, Version};
+use crate::error::Error;
+use crate::prune::PrunePolicy;
+
+/// The prefix of the names of the versions created by execute_on_fake_version.
+pub const FAKE_VERSION_PREFIX: &str = "tmp-autoedit-";
+
+/// How long a fake version may live before being considered as leftover by
+/// sweep_fake_versions. This leaves plenty of time to the runs still using theirs.
+const FAKE_VERSION_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Delete a temporary version when going out of scope, even if we are unwinding from a panic.
+struct TempVersion<'d, 'a> {
+    domain: &'d Domain<'a>,
+    version: Version,
+}
+
+impl<'d, 'a> Drop for TempVersion<'d, 'a> {
+    fn drop(&mut self) {
+        // Failures are tolerable, sweep_fake_versions will get rid of the version later on
+        let _ = self.domain.delete_version(&self.version);
+    }
+}
+
+impl<'a> Domain<'a> {
+    // Online.net api is buggy, and we cannot directly edit a record in the current zone (as is
+    // expected per the API docs), BUT we can update the zone by lying about the zone
+    // version we are updating: we create a fake version, and we ask the API servers to
+    // update a record in the active zone (specified by its ID), while saying it is in
+    // the new version we just created. This call succeeds and edit the current
+    // version, instead of telling us that this record doesn't exist in the new
+    // version. I love that kind of bugs (but I hope hope it's not as security issue!) ;)
+    //
+    // As this relies on an undocumented behavior, which may silently stop working, prefer
+    // modify_active: it checks that the edits took effect, and with Strategy::Auto, falls back
+    // to a new version when they didn't.
+    // The fake version is always deleted, even if 'f' fails or panics, and the result of 'f'
+    // is returned whether or not its deletion succeeds.
+    pub fn execute_on_fake_version<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: Fn(&Domain, &Version) -> Result<R, Error>,
+    {
+        // Best effort: leftovers of previous runs are not our main concern
+        let _ = self.sweep_fake_versions();
+
+        let _lock = self.lock()?;
+        let fake_version = TempVersion {
+            domain: self,
+            version: self
+                .add_version(&timestamped_name(FAKE_VERSION_PREFIX.trim_end_matches('-')))?,
+        };
+
+        let res = f(self, &fake_version.version);
+        drop(fake_version);
+
+        // we need to re-enable the current version to tell the dns servers to reload the zone,
+        // which may have been partially modified even if 'f' failed
+        let reload = self
+            .get_current_version()
+            .and_then(|version| self.enable_version(&version));
+
+        let res = res?;
+        reload?;
+        Ok(res)
+    }
+
+    /// Delete the fake versions left behind by execute_on_fake_version (e.g. because the
+    /// program was killed), and return them.
+    pub fn sweep_fake_versions(&self) -> Result<Vec<Version>, Error> {
+        self.prune_versions(
+            &PrunePolicy::new()
+                .name_prefix(FAKE_VERSION_PREFIX)
+                .older_than(FAKE_VERSION_MAX_AGE),
+        )
+    }
+}
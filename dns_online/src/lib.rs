@@ -1,7 +1,27 @@
+pub mod accounts;
 mod api;
 pub use crate::api::*;
-pub mod net;
+pub mod bind;
+pub mod clone;
+pub mod diff;
+pub mod dnssec;
 pub mod error;
-mod bind;
+pub mod format;
+mod inplace;
+pub use crate::inplace::FAKE_VERSION_PREFIX;
+pub mod lint;
+pub mod lock;
+pub mod modify;
+pub mod name;
+pub mod net;
+pub mod prune;
+pub mod query;
+pub mod resolver;
+pub mod set;
+pub mod snapshot;
+pub mod sync;
+pub mod transaction;
+mod verify;
+pub mod zonefile;
 
-pub static API_URL: &'static str = "https://api.online.net/api/v1";
+pub static API_URL: &str = "https://api.online.net/api/v1";
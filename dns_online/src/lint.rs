@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use crate::api::{Domain, Record, Version};
+use crate::error::Error;
+use crate::format::ZoneFormat;
+use crate::name::DomainName;
+use crate::net::DNSType;
+use crate::set::txt_content;
+
+/// How bad a finding is.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    /// Most likely a mistake, but the zone still resolves
+    Warning,
+    /// The zone is broken
+    Error,
+}
+
+/// The mistakes lint looks for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Check {
+    /// A CNAME record shares its name with other records
+    CnameAndOtherData,
+    /// The apex of the zone is a CNAME
+    CnameAtApex,
+    /// A MX, NS or SRV record points to an alias instead of a canonical name
+    TargetIsCname,
+    /// The same record appears several times
+    Duplicate,
+    /// A name has several SPF policies, which makes SPF checks fail
+    MultipleSpf,
+    /// A record points to a name of the zone which doesn't exist
+    DanglingTarget,
+    /// The records of the same name and type have different TTLs
+    InconsistentTtl,
+    /// An ACME challenge was left behind (e.g. by an interrupted renewal)
+    AcmeChallenge,
+}
+
+/// A mistake found in a zone by lint.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub check: Check,
+    pub severity: Severity,
+    /// The name the mistake was found at
+    pub name: DomainName,
+    pub message: String,
+    /// The records involved
+    pub records: Vec<Record>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.name.to_fqdn(), self.message)
+    }
+}
+
+/// The name a CNAME, MX, NS or SRV record points to.
+fn target(record: &Record, origin: &DomainName) -> Option<DomainName> {
+    match record.record_type {
+        DNSType::CNAME | DNSType::MX | DNSType::NS | DNSType::SRV => {
+            let data = record.key(origin).data;
+            let target = data.split_whitespace().last()?;
+            // "." is the null MX of the domains accepting no mail
+            if target == "." {
+                return None;
+            }
+            Some(DomainName::new(target))
+        }
+        _ => None,
+    }
+}
+
+fn is_spf(record: &Record) -> bool {
+    record.record_type == DNSType::TXT
+        && txt_content(&record.data)
+            .to_ascii_lowercase()
+            .starts_with("v=spf1")
+}
+
+/// Look for common mistakes in 'records', which belong to the zone 'origin'.
+/// The findings are sorted by name.
+pub fn lint(records: &[Record], origin: &DomainName) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut finding =
+        |check, severity, name: &DomainName, message: String, records: Vec<Record>| {
+            findings.push(Finding {
+                check,
+                severity,
+                name: name.clone(),
+                message,
+                records,
+            })
+        };
+
+    let mut names: BTreeMap<DomainName, Vec<&Record>> = BTreeMap::new();
+    for r in records {
+        names.entry(r.domain_name(origin)).or_default().push(r);
+    }
+    let is_cname = |name: &DomainName| {
+        names
+            .get(name)
+            .is_some_and(|records| records.iter().any(|r| r.record_type == DNSType::CNAME))
+    };
+
+    for (name, records) in &names {
+        let owned = |filter: &dyn Fn(&Record) -> bool| -> Vec<Record> {
+            records
+                .iter()
+                .filter(|r| filter(r))
+                .map(|&r| r.clone())
+                .collect()
+        };
+
+        let cnames = records
+            .iter()
+            .filter(|r| r.record_type == DNSType::CNAME)
+            .count();
+        if cnames > 0 && records.len() > 1 {
+            finding(
+                Check::CnameAndOtherData,
+                Severity::Error,
+                name,
+                "a CNAME record cannot coexist with other records".into(),
+                owned(&|_| true),
+            );
+        }
+        if cnames > 0 && name == origin {
+            finding(
+                Check::CnameAtApex,
+                Severity::Error,
+                name,
+                "the apex of the zone cannot be a CNAME".into(),
+                owned(&|r| r.record_type == DNSType::CNAME),
+            );
+        }
+
+        let (mut seen, mut reported) = (HashSet::new(), HashSet::new());
+        for r in records {
+            let key = r.key(origin);
+            if !seen.insert(key.clone()) && reported.insert(key) {
+                finding(
+                    Check::Duplicate,
+                    Severity::Warning,
+                    name,
+                    format!(
+                        "duplicate {} record '{}'",
+                        String::from(&r.record_type),
+                        r.data
+                    ),
+                    owned(&|x| x.same_as(r, origin)),
+                );
+            }
+        }
+
+        let spf = owned(&is_spf);
+        if spf.len() > 1 {
+            finding(
+                Check::MultipleSpf,
+                Severity::Error,
+                name,
+                format!("{} SPF policies, there must be at most one", spf.len()),
+                spf,
+            );
+        }
+
+        let mut types: Vec<DNSType> = records.iter().map(|r| r.record_type).collect();
+        types.sort_by_key(|t| t.code());
+        types.dedup();
+        for t in types {
+            let rrset = owned(&|r| r.record_type == t);
+            if rrset.iter().any(|r| r.ttl != rrset[0].ttl) {
+                finding(
+                    Check::InconsistentTtl,
+                    Severity::Warning,
+                    name,
+                    format!("the {} records have different TTLs", String::from(&t)),
+                    rrset,
+                );
+            }
+        }
+
+        if name.labels().next() == Some("_acme-challenge") {
+            let challenges = owned(&|r| r.record_type == DNSType::TXT);
+            if !challenges.is_empty() {
+                finding(
+                    Check::AcmeChallenge,
+                    Severity::Warning,
+                    name,
+                    "leftover ACME challenge".into(),
+                    challenges,
+                );
+            }
+        }
+
+        for r in records {
+            let target = match target(r, origin) {
+                Some(target) => target,
+                None => continue,
+            };
+            let record_type = String::from(&r.record_type);
+            if r.record_type != DNSType::CNAME && is_cname(&target) {
+                finding(
+                    Check::TargetIsCname,
+                    Severity::Error,
+                    name,
+                    format!(
+                        "the {} record points to {}, which is a CNAME",
+                        record_type,
+                        target.to_fqdn()
+                    ),
+                    vec![(*r).clone()],
+                );
+            }
+            // we can only tell for the names of the zone
+            if target.is_subdomain_of(origin) && !names.contains_key(&target) {
+                finding(
+                    Check::DanglingTarget,
+                    Severity::Error,
+                    name,
+                    format!(
+                        "the {} record points to {}, which doesn't exist",
+                        record_type,
+                        target.to_fqdn()
+                    ),
+                    vec![(*r).clone()],
+                );
+            }
+        }
+    }
+    findings
+}
+
+/// Same as lint, with the records of the file at 'path' (see ZoneFormat::read_file).
+pub fn lint_file(
+    path: &Path,
+    format: &dyn ZoneFormat,
+    origin: &DomainName,
+) -> Result<Vec<Finding>, Error> {
+    Ok(lint(&format.read_file(path, origin)?, origin))
+}
+
+impl<'a> Domain<'a> {
+    /// Look for common mistakes in the version 'version' of the zone. See lint.
+    pub fn lint_version(&self, version: &Version) -> Result<Vec<Finding>, Error> {
+        Ok(lint(&self.get_zone_records(version)?, &self.origin()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    /// The checks that failed on 'records', with the name they failed at.
+    fn checks(records: &[Record]) -> Vec<(Check, String)> {
+        lint(records, &origin())
+            .into_iter()
+            .map(|f| (f.check, f.name.to_fqdn()))
+            .collect()
+    }
+
+    #[test]
+    fn clean_zone() {
+        let records = vec![
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("@", "MX", "10 mail", 3600),
+            Record::new("@", "TXT", "\"v=spf1 mx -all\"", 3600),
+            Record::new("mail", "A", "192.0.2.2", 3600),
+            Record::new("www", "CNAME", "@", 3600),
+        ];
+        assert_eq!(checks(&records), vec![]);
+    }
+
+    #[test]
+    fn cname_and_other_data() {
+        let records = vec![
+            Record::new("www", "CNAME", "example.net.", 3600),
+            Record::new("www", "TXT", "\"hello\"", 3600),
+        ];
+        let findings = lint(&records, &origin());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, Check::CnameAndOtherData);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].records.len(), 2);
+    }
+
+    #[test]
+    fn cname_at_apex() {
+        let records = vec![Record::new("@", "CNAME", "example.net.", 3600)];
+        assert_eq!(
+            checks(&records),
+            vec![(Check::CnameAtApex, "example.com.".to_string())]
+        );
+    }
+
+    #[test]
+    fn mx_pointing_to_a_cname() {
+        let records = vec![
+            Record::new("@", "MX", "10 mail", 3600),
+            Record::new("mail", "CNAME", "mx.example.net.", 3600),
+        ];
+        assert_eq!(
+            checks(&records),
+            vec![(Check::TargetIsCname, "example.com.".to_string())]
+        );
+    }
+
+    #[test]
+    fn null_mx() {
+        let records = vec![Record::new("@", "MX", "0 .", 3600)];
+        assert_eq!(checks(&records), vec![]);
+    }
+
+    #[test]
+    fn dangling_targets() {
+        let records = vec![
+            Record::new("@", "MX", "10 mail", 3600),
+            Record::new("www", "CNAME", "www.example.net.", 3600),
+        ];
+        // only the target inside the zone can be known to be missing
+        assert_eq!(
+            checks(&records),
+            vec![(Check::DanglingTarget, "example.com.".to_string())]
+        );
+    }
+
+    #[test]
+    fn multiple_spf() {
+        let records = vec![
+            Record::new("@", "TXT", "\"v=spf1 mx -all\"", 3600),
+            Record::new("@", "TXT", "\"V=SPF1 a -all\"", 3600),
+            Record::new("@", "TXT", "\"google-site-verification=abc\"", 3600),
+        ];
+        let findings = lint(&records, &origin());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, Check::MultipleSpf);
+        assert_eq!(findings[0].records.len(), 2);
+    }
+
+    #[test]
+    fn inconsistent_ttls() {
+        let records = vec![
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("@", "A", "192.0.2.2", 300),
+            Record::new("@", "AAAA", "2001:db8::1", 300),
+        ];
+        let findings = lint(&records, &origin());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, Check::InconsistentTtl);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0]
+            .records
+            .iter()
+            .all(|r| r.record_type == DNSType::A));
+    }
+
+    #[test]
+    fn leftover_acme_challenge() {
+        let records = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("_acme-challenge.www", "TXT", "\"token\"", 60),
+        ];
+        assert_eq!(
+            checks(&records),
+            vec![(
+                Check::AcmeChallenge,
+                "_acme-challenge.www.example.com.".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::api::Domain;
+use crate::error::Error;
+
+/// How often we check whether a lock held by somebody else was released.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where the lock files are stored, and how long to wait for a lock.
+/// The default directory belongs to the current user: /run/lock/le_dns_online for root, and a
+/// directory of its own in the temporary directory for everybody else. To serialise the runs of
+/// several users (e.g. acme.sh running as root and your cron jobs), give them a directory they
+/// all can write to, but that the other users can't (e.g. group-writable).
+#[derive(Clone, Debug)]
+pub struct LockConfig {
+    pub directory: PathBuf,
+    pub timeout: Duration,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        LockConfig {
+            directory: default_lock_directory(),
+            timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn default_lock_directory() -> PathBuf {
+    match unsafe { libc::geteuid() } {
+        0 => PathBuf::from("/run/lock/le_dns_online"),
+        uid => std::env::temp_dir().join(format!("le_dns_online-{}", uid)),
+    }
+}
+
+#[cfg(not(unix))]
+fn default_lock_directory() -> PathBuf {
+    std::env::temp_dir().join("le_dns_online")
+}
+
+/// An advisory lock on a domain, shared by every process using the same lock directory.
+/// This serialises the modifications of a zone by concurrent programs (acme.sh, cron jobs...)
+/// instead of letting them overwrite each other's changes.
+/// The lock is released when this object is dropped (or when the process exits).
+#[derive(Debug)]
+pub struct DomainLock {
+    file: File,
+    path: PathBuf,
+}
+
+/// Create the lock directory 'directory' if needed, accessible to the current user only.
+/// An existing directory must belong to the current user or to root, and must not be writable
+/// by everyone: anybody able to create a lock file there could hold our locks forever.
+fn create_lock_directory(directory: &Path) -> Result<(), Error> {
+    if !directory.is_dir() {
+        fs::create_dir_all(directory)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(directory, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::metadata(directory)?;
+        let owner = metadata.uid();
+        if (owner != 0 && owner != unsafe { libc::geteuid() }) || metadata.mode() & 0o002 != 0 {
+            return Err(Error::InsecureLockDirectory(directory.into()));
+        }
+    }
+    Ok(())
+}
+
+/// Open (and create if needed) the lock file 'path'.
+/// A lock file created by another user may only be readable, which is enough to lock it.
+fn open_lock_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .or_else(|_| File::open(path))
+}
+
+impl DomainLock {
+    /// Take the lock for the domain 'domain_name', waiting at most 'config.timeout' for it to
+    /// be released by its current owner.
+    pub fn acquire(domain_name: &str, config: &LockConfig) -> Result<DomainLock, Error> {
+        create_lock_directory(&config.directory)?;
+        let file_name: String = domain_name
+            .to_ascii_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = config.directory.join(format!("{}.lock", file_name));
+        let file = open_lock_file(&path)?;
+
+        let start = Instant::now();
+        while file.try_lock_exclusive().is_err() {
+            if start.elapsed() >= config.timeout {
+                return Err(Error::LockTimeout(path));
+            }
+            thread::sleep(LOCK_POLL_INTERVAL);
+        }
+        Ok(DomainLock { file, path })
+    }
+
+    /// The path of the lock file.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Drop for DomainLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+impl<'a> Domain<'a> {
+    /// Make the operations modifying the domain (transactions, syncs, in-place updates, version
+    /// creations, pruning and DNSSEC changes) take the lock of this domain first. See
+    /// DomainLock.
+    pub fn with_lock(mut self, config: LockConfig) -> Self {
+        self.lock = Some(config);
+        self
+    }
+
+    /// Take the lock of this domain, if locking was enabled with with_lock.
+    pub fn lock(&self) -> Result<Option<DomainLock>, Error> {
+        match &self.lock {
+            Some(config) => Ok(Some(DomainLock::acquire(&self.name, config)?)),
+            None => Ok(None),
+        }
+    }
+}
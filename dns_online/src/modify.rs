@@ -0,0 +1,173 @@
+use crate::api::{timestamped_name, Domain, Record, Version};
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::transaction::Change;
+
+/// How to apply changes to the active zone.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// Stage the changes in a copy of the active version, and enable it (see ZoneTransaction)
+    NewVersion,
+    /// Edit the records of the active version directly (see Domain::execute_on_fake_version).
+    /// Only deletions and updates can be applied this way.
+    InPlace,
+    /// Edit in place when possible, and apply the changes that could not be applied in place
+    /// in a new version
+    Auto,
+}
+
+/// Look up the records of the active zone, 'records', targeted by 'changes'.
+fn in_place_targets(
+    changes: &[Change],
+    records: &[Record],
+    origin: &DomainName,
+) -> Result<Vec<Record>, Error> {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Add(_) => Err(Error::InPlaceUnsupported),
+            Change::Delete(record) | Change::Update { record, .. } => records
+                .iter()
+                .find(|x| x.same_as(record, origin))
+                .cloned()
+                .ok_or(Error::NoRecord),
+        })
+        .collect()
+}
+
+impl<'a> Domain<'a> {
+    /// Apply 'changes' to the active zone, following 'strategy', and return the strategy that
+    /// was actually used (Strategy::NewVersion if Strategy::Auto had to fall back to it, even
+    /// partially).
+    pub fn modify_active(&self, changes: &[Change], strategy: Strategy) -> Result<Strategy, Error> {
+        self.modify(changes, strategy, false)
+    }
+
+    /// Same as modify_active, then make sure the active zone holds its former records with
+    /// 'changes' applied.
+    /// On mismatch, the changes made in a new version are rolled back (see
+    /// ZoneTransaction::set_verify), but those made in place cannot be: Error::VerificationFailed
+    /// then only describes the discrepancy.
+    pub fn modify_active_verified(
+        &self,
+        changes: &[Change],
+        strategy: Strategy,
+    ) -> Result<Strategy, Error> {
+        self.modify(changes, strategy, true)
+    }
+
+    fn modify(
+        &self,
+        changes: &[Change],
+        strategy: Strategy,
+        verify: bool,
+    ) -> Result<Strategy, Error> {
+        if strategy == Strategy::NewVersion
+            || (strategy == Strategy::Auto && changes.iter().any(|c| matches!(c, Change::Add(_))))
+        {
+            self.modify_in_new_version(changes, verify)?;
+            return Ok(Strategy::NewVersion);
+        }
+
+        let origin = self.origin();
+        let version = self.get_current_version()?;
+        let records = self.get_zone_records(&version)?;
+        let targets = in_place_targets(changes, &records, &origin)?;
+        match self.modify_in_place(changes, &targets) {
+            // with Strategy::Auto, some changes may have been applied before the API refused
+            // one, the others are applied in a new version below
+            Err(Error::ApiError(_)) if strategy == Strategy::Auto => {}
+            res => res?,
+        }
+
+        let (mut applied, mut remaining) = (Vec::new(), Vec::new());
+        for (change, target) in changes.iter().zip(&targets) {
+            if self.applied_in_place(&version, change, target)? {
+                applied.push(change);
+            } else if strategy == Strategy::InPlace {
+                return Err(Error::InPlaceUnsupported);
+            } else {
+                remaining.push(change.clone());
+            }
+        }
+        if verify {
+            let mut expected = records;
+            for change in applied {
+                change.apply_to(&mut expected, &origin)?;
+            }
+            self.verify_in_place(&version, &expected)?;
+        }
+        if remaining.is_empty() {
+            return Ok(Strategy::InPlace);
+        }
+        self.modify_in_new_version(&remaining, verify)?;
+        Ok(Strategy::NewVersion)
+    }
+
+    fn modify_in_new_version(&self, changes: &[Change], verify: bool) -> Result<Version, Error> {
+        let mut transaction = self.begin_transaction(&timestamped_name("autoedit"))?;
+        transaction.set_verify(verify);
+        for change in changes {
+            transaction.apply(change.clone())?;
+        }
+        transaction.commit()
+    }
+
+    fn modify_in_place(&self, changes: &[Change], targets: &[Record]) -> Result<(), Error> {
+        self.execute_on_fake_version(|domain, fake_version| {
+            for (change, target) in changes.iter().zip(targets) {
+                match change {
+                    Change::Add(_) => return Err(Error::InPlaceUnsupported),
+                    Change::Delete(_) => domain.delete_record(fake_version, target)?,
+                    Change::Update { new_value, .. } => {
+                        domain.update_version_record(fake_version, target, new_value)?
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Check whether 'change', whose record in the active zone is 'target', is visible in
+    /// 'version'.
+    fn applied_in_place(
+        &self,
+        version: &Version,
+        change: &Change,
+        target: &Record,
+    ) -> Result<bool, Error> {
+        let current = match self.get_record(version, target.id) {
+            Ok(r) => Some(r),
+            // the record is gone
+            Err(Error::ApiError(e)) if e.status_code == 404 => None,
+            Err(e) => return Err(e),
+        };
+        Ok(match (change, current) {
+            (Change::Add(_), _) => false,
+            (Change::Delete(_), current) => current.is_none(),
+            (Change::Update { new_value, .. }, Some(r)) => {
+                let expected = Record {
+                    data: new_value.clone(),
+                    ..target.clone()
+                };
+                r.same_as(&expected, &self.origin())
+            }
+            (Change::Update { .. }, None) => false,
+        })
+    }
+
+    /// Update a record in the current version, by replacing its value.
+    /// This is a shorthand for modify_active with Strategy::Auto.
+    pub fn update_current_version_record(
+        &self,
+        record: &Record,
+        new_value: &str,
+    ) -> Result<(), Error> {
+        let change = Change::Update {
+            record: record.clone(),
+            new_value: new_value.into(),
+        };
+        self.modify_active(&[change], Strategy::Auto)?;
+        Ok(())
+    }
+}
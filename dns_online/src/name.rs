@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::error::Error;
+
+/// Convert a (possibly internationalized) name to the ASCII form expected by the API, by
+/// encoding its non-ASCII labels with punycode.
+/// Relative names, absolute names (with their trailing dot) and "@" keep their form, and
+/// names that are already in ASCII are returned untouched.
+pub fn to_ascii(name: &str) -> Result<String, Error> {
+    if name.is_ascii() {
+        return Ok(name.into());
+    }
+    let (name, absolute) = match name.strip_suffix('.') {
+        Some(x) => (x, true),
+        None => (name, false),
+    };
+    let mut res = idna::domain_to_ascii(name).map_err(|_| Error::InvalidName(name.to_owned()))?;
+    if absolute {
+        res.push('.');
+    }
+    Ok(res)
+}
+
+/// Convert a name to its Unicode form, for display purposes.
+/// This is the reverse of to_ascii, and names that cannot be decoded are returned untouched.
+pub fn to_unicode(name: &str) -> String {
+    if !name.contains("xn--") && !name.contains("XN--") {
+        return name.into();
+    }
+    match idna::domain_to_unicode(name) {
+        (res, Ok(())) => res,
+        (_, Err(_)) => name.into(),
+    }
+}
+
+/// A domain name, stored in its canonical form: in ASCII (internationalized labels are encoded
+/// with punycode), lowercase and without the trailing dot.
+/// The API is rather inconsistent in the way it returns record names (sometimes relative to
+/// the zone, sometimes absolute with a trailing dot), so comparing names as raw strings is
+/// bound to fail. Build a DomainName out of them instead, and compare these.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct DomainName(String);
+
+impl DomainName {
+    /// Build a name from an absolute one, with or without its trailing dot.
+    /// Names that cannot be converted to ASCII are kept as is (but lowercased), see parse
+    /// if you want to reject them instead.
+    pub fn new(name: &str) -> DomainName {
+        DomainName::parse(name).unwrap_or_else(|_| {
+            let name = name.strip_suffix('.').unwrap_or(name);
+            DomainName(name.to_lowercase())
+        })
+    }
+
+    /// Build a name from an absolute one, failing if it isn't a valid internationalized name.
+    pub fn parse(name: &str) -> Result<DomainName, Error> {
+        let name = to_ascii(name)?;
+        let name = name.strip_suffix('.').unwrap_or(&name);
+        Ok(DomainName(name.to_ascii_lowercase()))
+    }
+
+    /// Resolve 'name' against the zone 'origin', the way the API expects it:
+    /// - a name ending with a dot is absolute
+    /// - "@" and the empty string designate the origin itself
+    /// - a name that already ends with the origin is considered absolute (the API sometimes
+    ///   returns those without the trailing dot)
+    /// - anything else is relative to the origin
+    pub fn from_relative(name: &str, origin: &DomainName) -> DomainName {
+        if name.ends_with('.') {
+            return DomainName::new(name);
+        }
+        if name.is_empty() || name == "@" {
+            return origin.clone();
+        }
+        let name = DomainName::new(name);
+        if name.is_subdomain_of(origin) {
+            name
+        } else {
+            name.join(origin)
+        }
+    }
+
+    /// The root of the DNS tree.
+    pub fn root() -> DomainName {
+        DomainName(String::new())
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The canonical representation of the name, without its trailing dot.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The name with its trailing dot, e.g. "www.example.com.".
+    pub fn to_fqdn(&self) -> String {
+        format!("{}.", self.0)
+    }
+
+    /// The name in its Unicode form, e.g. "www.bücher.de" for "www.xn--bcher-kva.de".
+    pub fn to_unicode(&self) -> String {
+        to_unicode(&self.0)
+    }
+
+    /// Iterate over the labels of the name, starting from the leftmost one.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.0.split('.').filter(|x| !x.is_empty())
+    }
+
+    /// Compare two names in the canonical order of DNSSEC (RFC 4034): label by label, starting
+    /// from the rightmost one, so that a name comes right before its subdomains.
+    pub fn canonical_cmp(&self, other: &DomainName) -> Ordering {
+        let a: Vec<&str> = self.labels().collect();
+        let b: Vec<&str> = other.labels().collect();
+        a.iter().rev().cmp(b.iter().rev())
+    }
+
+    /// Return the name 'self.origin'.
+    pub fn join(&self, origin: &DomainName) -> DomainName {
+        match (self.is_root(), origin.is_root()) {
+            (true, _) => origin.clone(),
+            (_, true) => self.clone(),
+            _ => DomainName(format!("{}.{}", self.0, origin.0)),
+        }
+    }
+
+    /// Return true if 'self' is equal to or below 'parent' in the DNS tree.
+    /// Matching is done on label boundaries, so "foo.notexample.com" is not a subdomain of
+    /// "example.com".
+    pub fn is_subdomain_of(&self, parent: &DomainName) -> bool {
+        if parent.is_root() || self == parent {
+            return true;
+        }
+        self.0.len() > parent.0.len()
+            && self.0.ends_with(&parent.0)
+            && self.0.as_bytes()[self.0.len() - parent.0.len() - 1] == b'.'
+    }
+
+    /// Move the name from the zone 'from' to the zone 'to', e.g. "www.example.com" moved from
+    /// "example.com" to "example.org" gives "www.example.org".
+    /// Return None if the name is not inside 'from'.
+    pub fn rebase(&self, from: &DomainName, to: &DomainName) -> Option<DomainName> {
+        match self.relative_to(from)? {
+            "" => Some(to.clone()),
+            local_part => Some(DomainName(local_part.into()).join(to)),
+        }
+    }
+
+    /// Return the part of the name relative to 'origin' ("" for the origin itself), or None
+    /// if the name is not inside 'origin'.
+    pub fn relative_to(&self, origin: &DomainName) -> Option<&str> {
+        if !self.is_subdomain_of(origin) {
+            return None;
+        }
+        if origin.is_root() {
+            return Some(&self.0);
+        }
+        let len = self.0.len() - origin.0.len();
+        Some(self.0[0..len].trim_end_matches('.'))
+    }
+}
+
+impl From<&str> for DomainName {
+    fn from(e: &str) -> Self {
+        DomainName::new(e)
+    }
+}
+
+impl From<&String> for DomainName {
+    fn from(e: &String) -> Self {
+        DomainName::new(e)
+    }
+}
+
+impl fmt::Display for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -18,7 +18,7 @@ pub enum HTTPOp<'a> {
 }
 
 /// The various types of DNS entries you may add
-#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Debug)]
 pub enum DNSType {
     A,
     AAAA,
@@ -30,6 +30,22 @@ pub enum DNSType {
     SRV,
 }
 
+impl DNSType {
+    /// The numeric value of the type, as used on the wire.
+    pub fn code(self) -> u16 {
+        match self {
+            DNSType::A => 1,
+            DNSType::NS => 2,
+            DNSType::CNAME => 5,
+            DNSType::MX => 15,
+            DNSType::TXT => 16,
+            DNSType::AAAA => 28,
+            DNSType::SRV => 33,
+            DNSType::CAA => 257,
+        }
+    }
+}
+
 impl From<&DNSType> for String {
     fn from(e: &DNSType) -> Self {
         match e {
@@ -74,14 +90,14 @@ pub fn make_query(api_endpoint: &str, auth_token: &str) -> Result<Easy, curl::Er
 
     let mut http_headers = List::new();
     let mut auth: String = "Authorization: Bearer ".into();
-    auth.push_str(&auth_token);
+    auth.push_str(auth_token);
     http_headers.append(&auth)?;
     easy.http_headers(http_headers)?;
     Ok(easy)
 }
 
 fn attach_data(req: &mut Easy, data: &[FormData]) -> Result<(), Error> {
-    if data.len() == 0 {
+    if data.is_empty() {
         return Err(Error::InvalidPost);
     }
     // the data.len()*25 is just a very rough heuristic
@@ -144,10 +160,7 @@ where
 {
     let req = make_query(api_endpoint, auth_token)?;
 
-    let mut req = match configure(req) {
-        Ok(x) => x,
-        Err(e) => return Err(e.into()),
-    };
+    let mut req = configure(req).map_err(Into::into)?;
 
     let mut buf = Vec::new();
     {
@@ -162,7 +175,7 @@ where
         transfer.perform()?;
     }
     let res_code = req.response_code()?;
-    if res_code < 200 || res_code >= 400 {
+    if !(200..400).contains(&res_code) {
         return Err(Error::ApiError(APIError {
             url: req.effective_url()?.unwrap_or("<UNKNOWN URL>").into(),
             status_code: res_code,
@@ -170,10 +183,7 @@ where
         }));
     }
 
-    match parse(&buf) {
-        Ok(x) => Ok(x),
-        Err(e) => Err(e.into()),
-    }
+    parse(&buf).map_err(Into::into)
 }
 
 /// Return the json object parsed as a Rust object of type T
@@ -181,7 +191,7 @@ pub fn parse_json<T>(data: &[u8]) -> Result<T, serde_json::Error>
 where
     for<'de> T: serde::Deserialize<'de>,
 {
-    Ok(serde_json::from_slice(data)?)
+    serde_json::from_slice(data)
 }
 
 /// We don't care about this value, so we might as well throw it away. Note that you may still
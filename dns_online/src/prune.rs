@@ -0,0 +1,182 @@
+use regex::Regex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::{Domain, Version};
+use crate::error::Error;
+
+impl Version {
+    /// The creation time of the version, as encoded at the end of its name by this library
+    /// (e.g. "LE-challenge-1563184682" or "tmp-autoedit-1563184682000").
+    pub fn name_timestamp(&self) -> Option<SystemTime> {
+        let (_, suffix) = self.name.rsplit_once('-')?;
+        let value: u64 = suffix.parse().ok()?;
+        // anything this big is expressed in milliseconds
+        let since_epoch = if value > 100_000_000_000 {
+            Duration::from_millis(value)
+        } else {
+            Duration::from_secs(value)
+        };
+        Some(UNIX_EPOCH + since_epoch)
+    }
+
+    /// The creation time of the version, taken from the API if available, and from its name
+    /// otherwise (see name_timestamp).
+    pub fn creation_time(&self) -> Option<SystemTime> {
+        self.created().or_else(|| self.name_timestamp())
+    }
+}
+
+/// Select the versions of a zone to delete. All the criteria must match for a version to be
+/// selected, and the active version is never selected.
+/// A policy without any criterion selects nothing, so that a forgotten criterion never wipes
+/// the history of a zone.
+#[derive(Clone, Debug, Default)]
+pub struct PrunePolicy {
+    keep_latest: Option<usize>,
+    name_prefix: Option<String>,
+    name_regex: Option<Regex>,
+    older_than: Option<Duration>,
+}
+
+impl PrunePolicy {
+    /// A policy selecting nothing, until given a criterion.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never select the 'count' most recent versions among those matching the other criteria.
+    pub fn keep_latest(mut self, count: usize) -> Self {
+        self.keep_latest = Some(count);
+        self
+    }
+
+    /// Only select the versions whose name starts with 'prefix'.
+    pub fn name_prefix(mut self, prefix: &str) -> Self {
+        self.name_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Only select the versions whose name matches the regular expression 'regex'.
+    pub fn name_regex(mut self, regex: &str) -> Result<Self, Error> {
+        self.name_regex = Some(Regex::new(regex)?);
+        Ok(self)
+    }
+
+    /// Only select the versions older than 'age'. Versions whose age is unknown are kept.
+    pub fn older_than(mut self, age: Duration) -> Self {
+        self.older_than = Some(age);
+        self
+    }
+
+    /// Return true if no criterion was given.
+    pub fn is_empty(&self) -> bool {
+        self.keep_latest.is_none()
+            && self.name_prefix.is_none()
+            && self.name_regex.is_none()
+            && self.older_than.is_none()
+    }
+
+    fn matches(&self, version: &Version, now: SystemTime) -> bool {
+        if version.active {
+            return false;
+        }
+        if let Some(prefix) = &self.name_prefix {
+            if !version.name.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.name_regex {
+            if !regex.is_match(&version.name) {
+                return false;
+            }
+        }
+        if let Some(age) = self.older_than {
+            match version.creation_time() {
+                Some(created) if created + age < now => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Return the versions selected among 'versions', listed from the oldest to the newest.
+    pub fn select(&self, versions: &[Version]) -> Vec<Version> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let now = SystemTime::now();
+        let mut versions = versions.to_vec();
+        // Without the creation dates, rely on the API which lists the versions from the oldest
+        // to the newest
+        if versions.iter().all(|v| v.created().is_some()) {
+            versions.sort_by_key(|v| v.created());
+        }
+        versions.retain(|v| self.matches(v, now));
+        let selected = versions.len().saturating_sub(self.keep_latest.unwrap_or(0));
+        versions.truncate(selected);
+        versions
+    }
+}
+
+impl<'a> Domain<'a> {
+    /// Return the versions 'policy' would delete, without deleting anything.
+    pub fn versions_to_prune(&self, policy: &PrunePolicy) -> Result<Vec<Version>, Error> {
+        Ok(policy.select(&self.get_versions()?))
+    }
+
+    /// Delete the versions selected by 'policy', and return them.
+    pub fn prune_versions(&self, policy: &PrunePolicy) -> Result<Vec<Version>, Error> {
+        let _lock = self.lock()?;
+        let versions = self.versions_to_prune(policy)?;
+        for v in &versions {
+            self.delete_version(v)?;
+        }
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(name: &str, active: bool) -> Version {
+        Version {
+            uuid: name.into(),
+            name: name.into(),
+            active,
+            created_at: None,
+            updated_at: None,
+            extra: Default::default(),
+        }
+    }
+
+    fn names(versions: Vec<Version>) -> Vec<String> {
+        versions.into_iter().map(|v| v.name).collect()
+    }
+
+    #[test]
+    fn empty_policy_selects_nothing() {
+        let versions = vec![version("a", false), version("b", false), version("c", true)];
+        assert!(PrunePolicy::new().is_empty());
+        assert!(PrunePolicy::new().select(&versions).is_empty());
+    }
+
+    #[test]
+    fn keep_latest_applies_to_matching_versions() {
+        let versions = vec![
+            version("LE-challenge-1", false),
+            version("manual", false),
+            version("LE-challenge-2", false),
+            version("LE-challenge-3", false),
+            version("backup", false),
+            version("current", true),
+        ];
+        let policy = PrunePolicy::new().name_prefix("LE-").keep_latest(2);
+        assert_eq!(names(policy.select(&versions)), vec!["LE-challenge-1"]);
+        let policy = PrunePolicy::new().keep_latest(2);
+        assert_eq!(
+            names(policy.select(&versions)),
+            vec!["LE-challenge-1", "manual", "LE-challenge-2"]
+        );
+    }
+}
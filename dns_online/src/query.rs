@@ -0,0 +1,270 @@
+use regex::Regex;
+use std::ops::{Bound, RangeBounds};
+
+use crate::api::{Domain, Record, Version};
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::net::DNSType;
+use crate::set::normalise_data;
+
+#[derive(Clone, Debug)]
+enum NameFilter {
+    /// Resolved against the origin of the zone when the query is executed
+    Exact(String),
+    Subtree(String),
+    Glob(String),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug)]
+enum ValueFilter {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+/// Match 'text' against a shell-like 'pattern', where '*' matches any sequence of characters
+/// and '?' a single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last '*' in the pattern, and of the text when we met it
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A set of criteria to select records, either from a version of a zone or from a local list of
+/// records. All the criteria must match for a record to be selected.
+/// e.g. RecordQuery::new().record_type(DNSType::TXT).subtree("_acme-challenge") selects every
+/// TXT record used for the Let's Encrypt challenges.
+#[derive(Clone, Debug)]
+pub struct RecordQuery {
+    types: Vec<DNSType>,
+    names: Vec<NameFilter>,
+    values: Vec<ValueFilter>,
+    ttl: (Bound<usize>, Bound<usize>),
+    require_match: bool,
+}
+
+impl Default for RecordQuery {
+    fn default() -> Self {
+        RecordQuery {
+            types: Vec::new(),
+            names: Vec::new(),
+            values: Vec::new(),
+            ttl: (Bound::Unbounded, Bound::Unbounded),
+            require_match: false,
+        }
+    }
+}
+
+impl RecordQuery {
+    /// A query matching every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only select records of type 'record_type'. This can be called multiple times to select
+    /// multiple types.
+    pub fn record_type(mut self, record_type: DNSType) -> Self {
+        self.types.push(record_type);
+        self
+    }
+
+    /// Only select records whose type is one of 'record_types'.
+    pub fn record_types(mut self, record_types: &[DNSType]) -> Self {
+        self.types.extend_from_slice(record_types);
+        self
+    }
+
+    /// Only select records named 'name', which may be either relative to the zone or absolute.
+    pub fn name(mut self, name: &str) -> Self {
+        self.names.push(NameFilter::Exact(name.into()));
+        self
+    }
+
+    /// Only select records named 'name' or located below it, e.g. "_acme-challenge" matches
+    /// "_acme-challenge" and "_acme-challenge.www".
+    pub fn subtree(mut self, name: &str) -> Self {
+        self.names.push(NameFilter::Subtree(name.into()));
+        self
+    }
+
+    /// Only select records whose absolute name (lowercase, without the trailing dot) matches
+    /// the glob 'pattern', e.g. "*.example.com".
+    /// Internationalized names match in either their ASCII or their Unicode form, e.g.
+    /// "*.bücher.de" and "*.xn--bcher-kva.de" are equivalent.
+    pub fn name_glob(mut self, pattern: &str) -> Self {
+        self.names.push(NameFilter::Glob(
+            pattern.trim_end_matches('.').to_lowercase(),
+        ));
+        self
+    }
+
+    /// Only select records whose absolute name (lowercase, without the trailing dot) matches
+    /// the regular expression 'regex', in either its ASCII or its Unicode form (see name_glob).
+    pub fn name_regex(mut self, regex: &str) -> Result<Self, Error> {
+        self.names.push(NameFilter::Regex(Regex::new(regex)?));
+        Ok(self)
+    }
+
+    /// Only select records whose value is 'value' (compared once normalised, see
+    /// set::normalise_data).
+    pub fn value(mut self, value: &str) -> Self {
+        self.values.push(ValueFilter::Exact(value.into()));
+        self
+    }
+
+    /// Only select records whose (normalised) value contains 'value'.
+    pub fn value_contains(mut self, value: &str) -> Self {
+        self.values.push(ValueFilter::Contains(value.into()));
+        self
+    }
+
+    /// Only select records whose (normalised) value matches the regular expression 'regex'.
+    pub fn value_regex(mut self, regex: &str) -> Result<Self, Error> {
+        self.values.push(ValueFilter::Regex(Regex::new(regex)?));
+        Ok(self)
+    }
+
+    /// Only select records whose ttl is inside 'range', e.g. "..3600" or "300..=600".
+    pub fn ttl_range(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.ttl = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Return Error::NoRecord instead of an empty list when no record matches.
+    pub fn require_match(mut self) -> Self {
+        self.require_match = true;
+        self
+    }
+
+    /// Return true if 'record', which belongs to the zone 'origin', matches the query.
+    pub fn matches(&self, record: &Record, origin: &DomainName) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&record.record_type) {
+            return false;
+        }
+        if !self.ttl.contains(&record.ttl) {
+            return false;
+        }
+
+        let name = record.domain_name(origin);
+        let unicode = name.to_unicode();
+        let names_match = self.names.iter().all(|filter| match filter {
+            NameFilter::Exact(x) => DomainName::from_relative(x, origin) == name,
+            NameFilter::Subtree(x) => name.is_subdomain_of(&DomainName::from_relative(x, origin)),
+            NameFilter::Glob(x) => glob_match(x, name.as_str()) || glob_match(x, &unicode),
+            NameFilter::Regex(x) => x.is_match(name.as_str()) || x.is_match(&unicode),
+        });
+        if !names_match {
+            return false;
+        }
+
+        let data = normalise_data(record.record_type, &record.data, origin);
+        self.values.iter().all(|filter| match filter {
+            ValueFilter::Exact(x) => normalise_data(record.record_type, x, origin) == data,
+            ValueFilter::Contains(x) => data.contains(x.as_str()),
+            ValueFilter::Regex(x) => x.is_match(&data),
+        })
+    }
+
+    /// Select the matching records in 'records', which belong to the zone 'origin'.
+    pub fn filter(&self, records: &[Record], origin: &DomainName) -> Result<Vec<Record>, Error> {
+        let res: Vec<Record> = records
+            .iter()
+            .filter(|r| self.matches(r, origin))
+            .cloned()
+            .collect();
+        if self.require_match && res.is_empty() {
+            return Err(Error::NoRecord);
+        }
+        Ok(res)
+    }
+
+    /// Select the matching records in the version 'version' of 'domain'.
+    pub fn run(&self, domain: &Domain, version: &Version) -> Result<Vec<Record>, Error> {
+        let records = domain.get_zone_records(version)?;
+        self.filter(&records, &domain.origin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_literal() {
+        assert!(glob_match("www.example.com", "www.example.com"));
+        assert!(!glob_match("www.example.com", "ww.example.com"));
+        assert!(!glob_match("www", "www.example.com"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("host?", "host1"));
+        assert!(!glob_match("host?", "host"));
+        assert!(!glob_match("host?", "host12"));
+    }
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything.example.com"));
+        assert!(glob_match("*.example.com", "www.example.com"));
+        assert!(glob_match("*.example.com", "a.b.example.com"));
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(glob_match(
+            "_acme-challenge.*",
+            "_acme-challenge.www.example.com"
+        ));
+        assert!(glob_match("a**b", "ab"));
+    }
+
+    #[test]
+    fn glob_star_backtracks() {
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(glob_match("a*b*c", "axbxbxc"));
+        assert!(!glob_match("a*b*c", "axbxbx"));
+        assert!(glob_match("*.*.com", "x.y.z.com"));
+    }
+
+    #[test]
+    fn internationalized_name_filters() {
+        let origin = DomainName::new("bücher.de");
+        let record = Record::new("www", "A", "192.0.2.1", 3600);
+        for query in &[
+            RecordQuery::new().name_glob("*.bücher.de"),
+            RecordQuery::new().name_glob("*.xn--bcher-kva.de"),
+            RecordQuery::new().name_glob("WWW.Bücher.de."),
+            RecordQuery::new()
+                .name_regex("^www\\.bücher\\.de$")
+                .unwrap(),
+            RecordQuery::new().name_regex("^www\\.xn--").unwrap(),
+            RecordQuery::new().name("www.bücher.de."),
+        ] {
+            assert!(query.matches(&record, &origin), "{:?}", query);
+        }
+        assert!(!RecordQuery::new()
+            .name_glob("*.bucher.de")
+            .matches(&record, &origin));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::api::Domain;
+use crate::name::DomainName;
+
+/// Find the zone a name belongs to, among a list of domains.
+/// Names are matched on label boundaries (so "foo.notexample.com" doesn't belong to
+/// "example.com") and the most specific zone wins, whatever the order in which the API
+/// returned the domains.
+#[derive(Clone, Debug)]
+pub struct ZoneResolver<'a> {
+    domains: Vec<Domain<'a>>,
+    skip_external: bool,
+}
+
+impl<'a> ZoneResolver<'a> {
+    pub fn new(domains: Vec<Domain<'a>>) -> Self {
+        ZoneResolver {
+            domains,
+            skip_external: false,
+        }
+    }
+
+    /// Ignore the domains hosted outside of online.net (see Domain::external), so that names
+    /// resolve to the most specific zone we can actually manage.
+    pub fn skip_external(mut self, skip: bool) -> Self {
+        self.skip_external = skip;
+        self
+    }
+
+    /// The domains this resolver selects from.
+    pub fn domains(&self) -> &[Domain<'a>] {
+        &self.domains
+    }
+
+    /// Return the index of the most specific domain containing 'name'.
+    fn find(&self, name: &DomainName) -> Option<usize> {
+        let mut res: Option<(usize, usize)> = None;
+        for (i, domain) in self.domains.iter().enumerate() {
+            if self.skip_external && domain.external {
+                continue;
+            }
+            let origin = domain.origin();
+            if !name.is_subdomain_of(&origin) {
+                continue;
+            }
+            let depth = origin.labels().count();
+            match res {
+                Some((_, best_depth)) if best_depth >= depth => {}
+                _ => res = Some((i, depth)),
+            }
+        }
+        res.map(|(i, _)| i)
+    }
+
+    /// Return the most specific domain containing 'name', along with the part of the name
+    /// relative to that domain ("@" if the name is the apex of the zone).
+    pub fn resolve(&self, name: &DomainName) -> Option<(&Domain<'a>, String)> {
+        let domain = &self.domains[self.find(name)?];
+        let local_part = relative_part(name, domain);
+        Some((domain, local_part))
+    }
+
+    /// Same as resolve, but consume the resolver to return the domain itself.
+    pub fn resolve_owned(mut self, name: &DomainName) -> Option<(Domain<'a>, String)> {
+        let domain = self.domains.swap_remove(self.find(name)?);
+        let local_part = relative_part(name, &domain);
+        Some((domain, local_part))
+    }
+}
+
+fn relative_part(name: &DomainName, domain: &Domain) -> String {
+    match name.relative_to(&domain.origin()) {
+        Some("") | None => "@".into(),
+        Some(x) => x.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(name: &str) -> Domain<'static> {
+        Domain {
+            api_key: "key",
+            id: 0,
+            name: name.into(),
+            dnssec: false,
+            external: false,
+            lock: None,
+        }
+    }
+
+    fn resolve(domains: &[&str], name: &str) -> Option<(String, String)> {
+        let resolver = ZoneResolver::new(domains.iter().map(|d| domain(d)).collect());
+        resolver
+            .resolve(&DomainName::new(name))
+            .map(|(d, local)| (d.name.clone(), local))
+    }
+
+    #[test]
+    fn labels_boundaries() {
+        assert_eq!(resolve(&["example.com"], "foo.notexample.com"), None);
+        assert_eq!(resolve(&["example.com"], "notexample.com"), None);
+        assert_eq!(
+            resolve(&["example.com"], "foo.example.com"),
+            Some(("example.com".into(), "foo".into()))
+        );
+    }
+
+    #[test]
+    fn most_specific_zone() {
+        let expected = Some(("sub.example.com".into(), "www".into()));
+        assert_eq!(
+            resolve(&["example.com", "sub.example.com"], "www.sub.example.com"),
+            expected
+        );
+        assert_eq!(
+            resolve(&["sub.example.com", "example.com"], "www.sub.example.com"),
+            expected
+        );
+        assert_eq!(
+            resolve(&["sub.example.com", "example.com"], "www.example.com"),
+            Some(("example.com".into(), "www".into()))
+        );
+    }
+
+    #[test]
+    fn apex() {
+        assert_eq!(
+            resolve(&["example.com"], "Example.COM."),
+            Some(("example.com".into(), "@".into()))
+        );
+    }
+
+    #[test]
+    fn skip_external() {
+        let mut external = domain("sub.example.com");
+        external.external = true;
+        let resolver = ZoneResolver::new(vec![domain("example.com"), external]).skip_external(true);
+        let (domain, local) = resolver
+            .resolve(&DomainName::new("www.sub.example.com"))
+            .unwrap();
+        assert_eq!(
+            (domain.name.as_str(), local.as_str()),
+            ("example.com", "www.sub")
+        );
+    }
+}
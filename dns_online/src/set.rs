@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::api::Record;
+use crate::name::DomainName;
+use crate::net::DNSType;
+
+/// The identity of a record: its absolute name, its type and its normalised data.
+/// Unlike Record, this doesn't account for the id (which changes with every version) nor for
+/// the ttl, so the same record read from two versions of a zone yields the same key.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct RecordKey {
+    pub name: DomainName,
+    pub record_type: DNSType,
+    pub data: String,
+}
+
+/// Extract the content of a TXT record, e.g. '"v=spf1 " "-all"' gives 'v=spf1 -all'.
+/// Data that isn't quoted is returned as is.
+pub fn txt_content(data: &str) -> String {
+    let data = data.trim();
+    if !data.starts_with('"') {
+        return data.into();
+    }
+    let mut res = String::with_capacity(data.len());
+    let mut in_string = false;
+    let mut chars = data.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                if let Some(c) = chars.next() {
+                    res.push(c);
+                }
+            }
+            c if in_string => res.push(c),
+            // whitespace between the strings
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Resolve a name found in the data of a record against 'origin'.
+pub(crate) fn normalise_target(target: &str, origin: &DomainName) -> String {
+    DomainName::from_relative(target, origin).to_fqdn()
+}
+
+/// The priority of the MX and SRV records whose priority is given neither in their data nor
+/// apart from it.
+pub fn default_priority(record_type: DNSType) -> usize {
+    match record_type {
+        DNSType::MX => 10,
+        _ => 0,
+    }
+}
+
+/// Split the priority of a MX or SRV record from the rest of its data, as it may or may not be
+/// part of it (see Record::priority).
+pub fn priority_and_fields(record: &Record) -> (usize, Vec<&str>) {
+    let fields: Vec<&str> = record.data.split_whitespace().collect();
+    let with_priority = match record.record_type {
+        DNSType::SRV => 4,
+        _ => 2,
+    };
+    if fields.len() == with_priority {
+        if let Ok(priority) = fields[0].parse() {
+            return (priority, fields[1..].to_vec());
+        }
+    }
+    let priority = record
+        .priority
+        .unwrap_or_else(|| default_priority(record.record_type));
+    (priority, fields)
+}
+
+/// Return the data of a record in a canonical form, so that equivalent values compare equal:
+/// whitespace is collapsed, TXT strings are unquoted, IP addresses are reformatted and the
+/// names appearing in CNAME, NS, MX and SRV records are made absolute.
+pub fn normalise_data(record_type: DNSType, data: &str, origin: &DomainName) -> String {
+    let fields: Vec<&str> = data.split_whitespace().collect();
+    match record_type {
+        DNSType::TXT => txt_content(data),
+        DNSType::A => match data.trim().parse::<Ipv4Addr>() {
+            Ok(ip) => ip.to_string(),
+            Err(_) => fields.join(" "),
+        },
+        DNSType::AAAA => match data.trim().parse::<Ipv6Addr>() {
+            Ok(ip) => ip.to_string(),
+            Err(_) => fields.join(" ").to_ascii_lowercase(),
+        },
+        DNSType::CNAME | DNSType::NS if fields.len() == 1 => normalise_target(fields[0], origin),
+        // The preference may or may not be part of the data
+        DNSType::MX if fields.len() == 1 => normalise_target(fields[0], origin),
+        DNSType::MX if fields.len() == 2 => {
+            format!("{} {}", fields[0], normalise_target(fields[1], origin))
+        }
+        DNSType::SRV if fields.len() == 4 => format!(
+            "{} {} {} {}",
+            fields[0],
+            fields[1],
+            fields[2],
+            normalise_target(fields[3], origin)
+        ),
+        // The priority may or may not be part of the data
+        DNSType::SRV if fields.len() == 3 => format!(
+            "{} {} {}",
+            fields[0],
+            fields[1],
+            normalise_target(fields[2], origin)
+        ),
+        DNSType::CAA if fields.len() >= 3 => format!(
+            "{} {} {}",
+            fields[0],
+            fields[1].to_ascii_lowercase(),
+            txt_content(&fields[2..].join(" "))
+        ),
+        _ => fields.join(" "),
+    }
+}
+
+/// Return the data of 'record' in a canonical form, see normalise_data.
+/// The priority of MX and SRV records is made part of the data when stored apart from it, so
+/// that records differing only by their priority don't compare equal, whether the priority is
+/// part of their data or not.
+pub fn normalise_record_data(record: &Record, origin: &DomainName) -> String {
+    match record.record_type {
+        DNSType::MX | DNSType::SRV if !record.data.trim().is_empty() => {
+            let (priority, fields) = priority_and_fields(record);
+            let data = format!("{} {}", priority, fields.join(" "));
+            normalise_data(record.record_type, &data, origin)
+        }
+        _ => normalise_data(record.record_type, &record.data, origin),
+    }
+}
+
+/// Sort 'records' in a canonical order, which doesn't depend on the order the API returned them
+/// in: by name (in the DNS order, see DomainName::canonical_cmp), then by type, then by data.
+pub fn canonical_order(records: &[Record], origin: &DomainName) -> Vec<Record> {
+    let mut records: Vec<(RecordKey, &Record)> =
+        records.iter().map(|r| (r.key(origin), r)).collect();
+    records.sort_by(|(a, ra), (b, rb)| {
+        a.name
+            .canonical_cmp(&b.name)
+            .then(a.record_type.code().cmp(&b.record_type.code()))
+            .then_with(|| a.data.cmp(&b.data))
+            .then_with(|| ra.priority.cmp(&rb.priority))
+            .then_with(|| ra.ttl.cmp(&rb.ttl))
+            .then_with(|| ra.data.cmp(&rb.data))
+    });
+    records.into_iter().map(|(_, r)| r.clone()).collect()
+}
+
+/// Remove the records sharing the same identity, keeping the first occurrence.
+pub fn dedup(records: &[Record], origin: &DomainName) -> Vec<Record> {
+    let mut seen = HashSet::new();
+    records
+        .iter()
+        .filter(|r| seen.insert(r.key(origin)))
+        .cloned()
+        .collect()
+}
+
+/// Return the records of 'a', followed by those of 'b' that are not in 'a'.
+pub fn union(a: &[Record], b: &[Record], origin: &DomainName) -> Vec<Record> {
+    let mut res = dedup(a, origin);
+    res.extend(difference(b, a, origin));
+    res
+}
+
+/// Return the records of 'a' that are not in 'b'.
+pub fn difference(a: &[Record], b: &[Record], origin: &DomainName) -> Vec<Record> {
+    let keys: HashSet<RecordKey> = b.iter().map(|r| r.key(origin)).collect();
+    let a = dedup(a, origin);
+    a.into_iter()
+        .filter(|r| !keys.contains(&r.key(origin)))
+        .collect()
+}
+
+/// Return the records of 'a' that are also in 'b'.
+pub fn intersection(a: &[Record], b: &[Record], origin: &DomainName) -> Vec<Record> {
+    let keys: HashSet<RecordKey> = b.iter().map(|r| r.key(origin)).collect();
+    let a = dedup(a, origin);
+    a.into_iter()
+        .filter(|r| keys.contains(&r.key(origin)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    fn with_priority(mut record: Record, priority: usize) -> Record {
+        record.priority = Some(priority);
+        record
+    }
+
+    #[test]
+    fn priority_is_part_of_the_identity() {
+        let a = with_priority(Record::new("@", "MX", "mail.example.com.", 3600), 10);
+        let b = with_priority(Record::new("@", "MX", "mail.example.com.", 3600), 50);
+        assert!(!a.same_as(&b, &origin()));
+        assert!(!Record::new("@", "MX", "10 mail", 3600)
+            .same_as(&Record::new("@", "MX", "50 mail", 3600), &origin()));
+    }
+
+    #[test]
+    fn priority_inside_or_apart_from_the_data() {
+        // as parsed from a zone file
+        let parsed = with_priority(Record::new("@", "MX", "mail.example.com.", 3600), 10);
+        // as returned by the API
+        let api = Record::new("example.com.", "MX", "10 mail.example.com.", 3600);
+        assert!(parsed.same_as(&api, &origin()));
+
+        let parsed = with_priority(
+            Record::new("_sip._tcp", "SRV", "5 5060 sip.example.com.", 3600),
+            10,
+        );
+        let api = Record::new("_sip._tcp", "SRV", "10 5 5060 sip", 3600);
+        assert!(parsed.same_as(&api, &origin()));
+    }
+
+    #[test]
+    fn missing_priority_uses_the_default() {
+        let implicit = Record::new("@", "MX", "mail", 3600);
+        let explicit = Record::new("@", "MX", "10 mail", 3600);
+        assert!(implicit.same_as(&explicit, &origin()));
+        assert_eq!(priority_and_fields(&implicit), (10, vec!["mail"]));
+    }
+
+    #[test]
+    fn normalise() {
+        let o = origin();
+        assert_eq!(normalise_data(DNSType::TXT, "\"a\" \"b\"", &o), "ab");
+        assert_eq!(
+            normalise_data(DNSType::AAAA, "2001:DB8:0::1", &o),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            normalise_data(DNSType::CNAME, "www", &o),
+            "www.example.com."
+        );
+        assert_eq!(
+            normalise_data(DNSType::SRV, "5  5060 sip", &o),
+            "5 5060 sip.example.com."
+        );
+        assert_eq!(
+            normalise_data(DNSType::CAA, "0 ISSUE \"letsencrypt.org\"", &o),
+            "0 issue letsencrypt.org"
+        );
+    }
+
+    fn names(records: &[Record]) -> Vec<(String, String, String)> {
+        records
+            .iter()
+            .map(|r| {
+                (
+                    r.domain_name(&origin()).to_fqdn(),
+                    String::from(&r.record_type),
+                    r.data.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn canonical_name_order() {
+        let records = vec![
+            Record::new("z", "A", "192.0.2.1", 3600),
+            Record::new("Z.a", "A", "192.0.2.1", 3600),
+            Record::new("a", "A", "192.0.2.1", 3600),
+            Record::new("*.z", "A", "192.0.2.1", 3600),
+            Record::new("yljkjljk.a", "A", "192.0.2.1", 3600),
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("zabc.a", "A", "192.0.2.1", 3600),
+        ];
+        let sorted: Vec<String> = names(&canonical_order(&records, &origin()))
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        // the example of RFC 4034, section 6.1
+        assert_eq!(
+            sorted,
+            vec![
+                "example.com.",
+                "a.example.com.",
+                "yljkjljk.a.example.com.",
+                "z.a.example.com.",
+                "zabc.a.example.com.",
+                "z.example.com.",
+                "*.z.example.com.",
+            ]
+        );
+    }
+
+    #[test]
+    fn canonical_type_and_data_order() {
+        let records = vec![
+            Record::new("@", "TXT", "\"b\"", 3600),
+            Record::new("@", "MX", "20 mail2", 3600),
+            Record::new("@", "A", "192.0.2.2", 3600),
+            Record::new("@", "TXT", "\"a\"", 3600),
+            Record::new("@", "MX", "10 mail1", 3600),
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("@", "NS", "ns1.example.net.", 3600),
+        ];
+        let sorted: Vec<(String, String)> = names(&canonical_order(&records, &origin()))
+            .into_iter()
+            .map(|(_, t, data)| (t, data))
+            .collect();
+        let expected = [
+            ("A", "192.0.2.1"),
+            ("A", "192.0.2.2"),
+            ("NS", "ns1.example.net."),
+            ("MX", "10 mail1"),
+            ("MX", "20 mail2"),
+            ("TXT", "\"a\""),
+            ("TXT", "\"b\""),
+        ];
+        assert_eq!(
+            sorted,
+            expected
+                .iter()
+                .map(|(t, d)| (t.to_string(), d.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn canonical_order_ignores_the_input_order() {
+        let mut records = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("www.example.com.", "AAAA", "2001:db8::1", 3600),
+            Record::new("@", "MX", "10 mail", 300),
+            Record::new("mail", "A", "192.0.2.2", 3600),
+            // same identity, only the ttl differs
+            Record::new("dup", "A", "192.0.2.3", 60),
+            Record::new("dup", "A", "192.0.2.3", 30),
+        ];
+        let sorted = canonical_order(&records, &origin());
+        records.reverse();
+        assert_eq!(canonical_order(&records, &origin()), sorted);
+        records.swap(0, 3);
+        assert_eq!(canonical_order(&records, &origin()), sorted);
+    }
+}
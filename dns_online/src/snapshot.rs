@@ -0,0 +1,166 @@
+use serde_derive::*;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::{query_available_domains, Domain, Record, Version};
+use crate::bind::{to_bind_with, BindStyle};
+use crate::error::Error;
+
+/// The name of the file describing the content of a snapshot directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A version of a zone saved in a snapshot.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotVersion {
+    pub version: Version,
+    /// Path of the records (in JSON), relative to the snapshot directory
+    pub records_file: String,
+    /// Path of the records (in the bind format), relative to the snapshot directory
+    pub bind_file: String,
+    pub record_count: usize,
+}
+
+/// A domain saved in a snapshot, with all its versions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotDomain {
+    pub id: usize,
+    pub name: String,
+    pub dnssec: bool,
+    pub external: bool,
+    pub versions: Vec<SnapshotVersion>,
+}
+
+/// The description of a snapshot, stored in the MANIFEST_FILE of its directory.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotManifest {
+    /// When the snapshot was taken, in seconds since the UNIX epoch
+    pub created_at: u64,
+    pub domains: Vec<SnapshotDomain>,
+}
+
+impl SnapshotManifest {
+    /// Read the manifest of the snapshot stored in 'directory'.
+    pub fn load(directory: &Path) -> Result<SnapshotManifest, Error> {
+        let data = fs::read(directory.join(MANIFEST_FILE))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Return the saved domain named 'domain_name'.
+    pub fn domain(&self, domain_name: &str) -> Option<&SnapshotDomain> {
+        self.domains.iter().find(|d| d.name == domain_name)
+    }
+}
+
+impl SnapshotDomain {
+    /// Return the saved version whose uuid is 'uuid', or the version that was active when the
+    /// snapshot was taken if 'uuid' is None.
+    pub fn version(&self, uuid: Option<&str>) -> Option<&SnapshotVersion> {
+        self.versions.iter().find(|v| match uuid {
+            Some(uuid) => v.version.uuid == uuid,
+            None => v.version.active,
+        })
+    }
+}
+
+impl SnapshotVersion {
+    /// Read the records of this version, from the snapshot stored in 'directory'.
+    pub fn records(&self, directory: &Path) -> Result<Vec<Record>, Error> {
+        let data = fs::read(directory.join(&self.records_file))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+/// Save every domain of the account, with all their versions and records, in 'directory'.
+/// Each version is saved in both JSON and bind format, in a sub-directory named after its
+/// domain, and the whole content is described by a manifest (see SnapshotManifest).
+pub fn snapshot_account(api_key: &str, directory: &Path) -> Result<SnapshotManifest, Error> {
+    snapshot_domains(&query_available_domains(api_key)?, directory)
+}
+
+/// Same as snapshot_account, for an arbitrary list of domains (e.g. those of several accounts,
+/// see Accounts).
+pub fn snapshot_domains(domains: &[Domain], directory: &Path) -> Result<SnapshotManifest, Error> {
+    let domains = domains
+        .iter()
+        .map(|domain| domain.snapshot(directory))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let manifest = SnapshotManifest {
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        domains,
+    };
+    fs::write(
+        directory.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+impl<'a> Domain<'a> {
+    /// Save all the versions of this domain in the sub-directory of 'directory' named after it.
+    /// See snapshot_account.
+    pub fn snapshot(&self, directory: &Path) -> Result<SnapshotDomain, Error> {
+        fs::create_dir_all(directory.join(&self.name))?;
+
+        let mut versions = Vec::new();
+        // external domains have no versions to save
+        let hosted_versions = if self.external {
+            Vec::new()
+        } else {
+            self.get_versions()?
+        };
+        for version in hosted_versions {
+            let records = self.get_zone_records(&version)?;
+            let records_file = format!("{}/{}.json", self.name, version.uuid);
+            let bind_file = format!("{}/{}.zone", self.name, version.uuid);
+            fs::write(
+                directory.join(&records_file),
+                serde_json::to_string_pretty(&records)?,
+            )?;
+            // canonical, so that snapshots kept in git give readable diffs
+            fs::write(
+                directory.join(&bind_file),
+                to_bind_with(&records, &self.origin(), &BindStyle::canonical()),
+            )?;
+            versions.push(SnapshotVersion {
+                version,
+                records_file,
+                bind_file,
+                record_count: records.len(),
+            });
+        }
+
+        Ok(SnapshotDomain {
+            id: self.id,
+            name: self.name.clone(),
+            dnssec: self.dnssec,
+            external: self.external,
+            versions,
+        })
+    }
+
+    /// Recreate a version saved in the snapshot stored in 'directory' as a new version named
+    /// 'version_name', and enable it if 'enable' is set.
+    /// 'uuid' selects the saved version to restore, and defaults to the version that was active
+    /// when the snapshot was taken.
+    pub fn restore_snapshot(
+        &self,
+        directory: &Path,
+        uuid: Option<&str>,
+        version_name: &str,
+        enable: bool,
+    ) -> Result<Version, Error> {
+        let manifest = SnapshotManifest::load(directory)?;
+        let saved = manifest
+            .domain(&self.name)
+            .and_then(|d| d.version(uuid))
+            .ok_or(Error::InvalidVersion)?;
+        let records = saved.records(directory)?;
+
+        self.create_version(version_name, &records, enable)
+    }
+}
@@ -0,0 +1,146 @@
+use crate::api::{timestamped_name, Domain, Record, Version};
+use crate::diff::ZoneDiff;
+use crate::error::Error;
+use crate::name::DomainName;
+use crate::net::DNSType;
+use crate::query::RecordQuery;
+use crate::transaction::{OnConflict, ZoneTransaction};
+
+/// Settings for Domain::sync.
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    version_name: Option<String>,
+    ignore: Vec<RecordQuery>,
+    protect_ns: bool,
+    verify: bool,
+    on_conflict: OnConflict,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            version_name: None,
+            ignore: Vec::new(),
+            protect_ns: true,
+            verify: false,
+            on_conflict: OnConflict::Fail,
+        }
+    }
+}
+
+impl SyncOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the version created to hold the changes ("sync-<timestamp>" by default).
+    pub fn version_name(mut self, name: &str) -> Self {
+        self.version_name = Some(name.into());
+        self
+    }
+
+    /// Leave the records matching 'query' alone: they are kept as is in the active zone, and
+    /// ignored in the desired records.
+    pub fn ignore(mut self, query: RecordQuery) -> Self {
+        self.ignore.push(query);
+        self
+    }
+
+    /// Leave the records of type 'record_type' alone.
+    pub fn ignore_type(self, record_type: DNSType) -> Self {
+        self.ignore(RecordQuery::new().record_type(record_type))
+    }
+
+    /// Leave the records named 'name' (relative to the zone or absolute) alone.
+    pub fn ignore_name(self, name: &str) -> Self {
+        self.ignore(RecordQuery::new().name(name))
+    }
+
+    /// Whether the NS records must be left alone (true by default), as removing them by
+    /// mistake is bound to break the whole zone.
+    pub fn protect_ns(mut self, protect_ns: bool) -> Self {
+        self.protect_ns = protect_ns;
+        self
+    }
+
+    /// Whether to check the active zone once the changes are enabled, and to roll back on
+    /// mismatch (false by default). See ZoneTransaction::set_verify.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// What to do if the zone is modified by someone else during the sync (OnConflict::Fail by
+    /// default). See ZoneTransaction::set_on_conflict.
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    fn is_ignored(&self, record: &Record, origin: &DomainName) -> bool {
+        (self.protect_ns && record.record_type == DNSType::NS)
+            || self.ignore.iter().any(|q| q.matches(record, origin))
+    }
+}
+
+/// The outcome of Domain::sync.
+#[derive(Clone, Debug)]
+pub struct SyncReport {
+    /// The changes made to the active zone
+    pub diff: ZoneDiff,
+    /// The version created and enabled, or None if the zone was already up to date
+    pub version: Option<Version>,
+}
+
+impl<'a> Domain<'a> {
+    /// Make the active zone match 'desired', except for the records ignored by 'options'.
+    /// The changes are applied through a ZoneTransaction.
+    /// Nothing is done if the zone is already up to date.
+    pub fn sync(&self, desired: &[Record], options: &SyncOptions) -> Result<SyncReport, Error> {
+        // the lock must be held while computing the changes, and until they are committed
+        let lock = self.lock()?;
+        let origin = self.origin();
+        let current = self.get_current_version()?;
+        let current_records = self.get_zone_records(&current)?;
+
+        let managed = |records: &[Record]| -> Vec<Record> {
+            records
+                .iter()
+                .filter(|r| !options.is_ignored(r, &origin))
+                .cloned()
+                .collect()
+        };
+        let diff = ZoneDiff::compute(&managed(&current_records), &managed(desired), &origin);
+        if diff.is_empty() {
+            return Ok(SyncReport {
+                diff,
+                version: None,
+            });
+        }
+
+        let version_name = options
+            .version_name
+            .clone()
+            .unwrap_or_else(|| timestamped_name("sync"));
+        let mut transaction = ZoneTransaction::begin_locked(self, &version_name, lock)?;
+        transaction.set_verify(options.verify);
+        transaction.set_on_conflict(options.on_conflict);
+        for change in diff.to_changes() {
+            transaction.apply(change)?;
+        }
+        let new_version = transaction.commit()?;
+
+        Ok(SyncReport {
+            diff,
+            version: Some(new_version),
+        })
+    }
+
+    /// Apply the changes listed in 'diff' to the version 'version', which MUST be inactive.
+    pub fn apply_diff(&self, version: &Version, diff: &ZoneDiff) -> Result<(), Error> {
+        for change in diff.to_changes() {
+            self.apply_change(version, &change)?;
+        }
+        Ok(())
+    }
+}
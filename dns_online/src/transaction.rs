@@ -0,0 +1,340 @@
+use crate::api::{Domain, Record, Version};
+use crate::diff::ZoneDiff;
+use crate::error::Error;
+use crate::lock::DomainLock;
+use crate::name::DomainName;
+
+/// A modification of a zone.
+/// Records to delete or update are looked up by identity (see RecordKey), so they may come
+/// from another version of the zone than the one being modified.
+#[derive(Clone, Debug)]
+pub enum Change {
+    Add(Record),
+    Delete(Record),
+    Update { record: Record, new_value: String },
+}
+
+impl Change {
+    /// Apply this change to 'records', a local copy of the records of the zone 'origin', the
+    /// way Domain::apply_change does on a version of the zone. This tells which records a
+    /// version should hold once the change is applied.
+    pub fn apply_to(&self, records: &mut Vec<Record>, origin: &DomainName) -> Result<(), Error> {
+        let position = |record: &Record| {
+            records
+                .iter()
+                .position(|x| x.same_as(record, origin))
+                .ok_or(Error::NoRecord)
+        };
+        match self {
+            Change::Add(record) => records.push(record.clone()),
+            Change::Delete(record) => {
+                let i = position(record)?;
+                records.remove(i);
+            }
+            Change::Update { record, new_value } => {
+                let i = position(record)?;
+                records[i].data = new_value.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ZoneDiff {
+    /// The list of changes needed to apply this diff. Modifications are expressed as a
+    /// deletion followed by an addition, so that both the value and the ttl are updated.
+    pub fn to_changes(&self) -> Vec<Change> {
+        let mut res = Vec::new();
+        for r in &self.removed {
+            res.push(Change::Delete(r.clone()));
+        }
+        for c in self.modified.iter().chain(self.ttl_changed.iter()) {
+            res.push(Change::Delete(c.old.clone()));
+            res.push(Change::Add(c.new.clone()));
+        }
+        for r in &self.added {
+            res.push(Change::Add(r.clone()));
+        }
+        res
+    }
+}
+
+impl<'a> Domain<'a> {
+    /// Look up the record matching 'record' in 'version'.
+    fn find_same_record(&self, version: &Version, record: &Record) -> Result<Record, Error> {
+        let origin = self.origin();
+        self.get_zone_records(version)?
+            .into_iter()
+            .find(|x| x.same_as(record, &origin))
+            .ok_or(Error::NoRecord)
+    }
+
+    /// Apply 'change' to the version 'version', which MUST be inactive.
+    pub fn apply_change(&self, version: &Version, change: &Change) -> Result<(), Error> {
+        match change {
+            Change::Add(record) => {
+                self.add_record(version, record)?;
+            }
+            Change::Delete(record) => {
+                let existing = self.find_same_record(version, record)?;
+                self.delete_record(version, &existing)?;
+            }
+            Change::Update { record, new_value } => {
+                let existing = self.find_same_record(version, record)?;
+                self.update_version_record(version, &existing, new_value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a transaction on the active zone. See ZoneTransaction.
+    pub fn begin_transaction(&self, version_name: &str) -> Result<ZoneTransaction<'_, 'a>, Error> {
+        ZoneTransaction::begin(self, version_name)
+    }
+}
+
+/// What to do when the active version changed between the start of a transaction and its
+/// commit, i.e. when somebody else modified the zone in the meantime.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnConflict {
+    /// Fail with Error::ConcurrentModification
+    Fail,
+    /// Stage the changes again on top of the new active version, and retry
+    Rebase,
+}
+
+/// How many times a transaction is rebased before giving up.
+const MAX_REBASES: usize = 5;
+
+/// A set of changes to the active zone, applied all at once.
+/// The changes are staged in a copy of the active version, which replaces it when the
+/// transaction is committed. If anything fails along the way, or if the transaction is
+/// dropped before being committed, the staging version is deleted, so that no half-built
+/// version is left on the account.
+/// Just before enabling the staging version, the transaction checks that the active version
+/// is still the one it was based on, to avoid silently discarding the changes made by someone
+/// else in the meantime (see OnConflict).
+pub struct ZoneTransaction<'d, 'a> {
+    domain: &'d Domain<'a>,
+    version_name: String,
+    base: Version,
+    /// The records of 'base' when the transaction started (or was last rebased)
+    base_records: Vec<Record>,
+    staging: Version,
+    changes: Vec<Change>,
+    verify: bool,
+    on_conflict: OnConflict,
+    done: bool,
+    _lock: Option<DomainLock>,
+}
+
+impl<'d, 'a> ZoneTransaction<'d, 'a> {
+    /// Copy the active zone of 'domain' into a new version named 'version_name', to stage the
+    /// changes.
+    /// The lock of the domain, if enabled (see Domain::with_lock), is held until the
+    /// transaction is committed or dropped.
+    pub fn begin(domain: &'d Domain<'a>, version_name: &str) -> Result<Self, Error> {
+        let lock = domain.lock()?;
+        Self::begin_locked(domain, version_name, lock)
+    }
+
+    /// Same as begin, with the lock of the domain already taken by the caller.
+    pub(crate) fn begin_locked(
+        domain: &'d Domain<'a>,
+        version_name: &str,
+        lock: Option<DomainLock>,
+    ) -> Result<Self, Error> {
+        let base = domain.get_current_version()?;
+        let records = domain.get_zone_records(&base)?;
+        let staging = domain.add_version(version_name)?;
+        // From now on, dropping the transaction deletes the staging version
+        let transaction = ZoneTransaction {
+            domain,
+            version_name: version_name.into(),
+            base,
+            base_records: records,
+            staging,
+            changes: Vec::new(),
+            verify: false,
+            on_conflict: OnConflict::Fail,
+            done: false,
+            _lock: lock,
+        };
+        domain.set_zone_entries(&transaction.staging, &transaction.base_records)?;
+        Ok(transaction)
+    }
+
+    /// The version that was active when the transaction started.
+    pub fn base(&self) -> &Version {
+        &self.base
+    }
+
+    /// The version holding the staged changes.
+    pub fn staging(&self) -> &Version {
+        &self.staging
+    }
+
+    /// The changes staged so far.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Whether to check, once the staging version is enabled, that the active zone holds the
+    /// records of the base version with the staged changes applied (see expected_records), and
+    /// to go back to the previous version if it doesn't (see Domain::enable_version_verified).
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// What to do if the active version changed before the commit (OnConflict::Fail by default).
+    pub fn set_on_conflict(&mut self, on_conflict: OnConflict) {
+        self.on_conflict = on_conflict;
+    }
+
+    /// Stage 'change'.
+    pub fn apply(&mut self, change: Change) -> Result<(), Error> {
+        self.domain.apply_change(&self.staging, &change)?;
+        self.changes.push(change);
+        Ok(())
+    }
+
+    /// Stage the addition of 'record'.
+    pub fn add(&mut self, record: &Record) -> Result<(), Error> {
+        self.apply(Change::Add(record.clone()))
+    }
+
+    /// Stage the deletion of 'record'.
+    pub fn delete(&mut self, record: &Record) -> Result<(), Error> {
+        self.apply(Change::Delete(record.clone()))
+    }
+
+    /// Stage the replacement of the value of 'record' by 'new_value'.
+    pub fn update(&mut self, record: &Record, new_value: &str) -> Result<(), Error> {
+        self.apply(Change::Update {
+            record: record.clone(),
+            new_value: new_value.into(),
+        })
+    }
+
+    /// The records the zone should hold once the transaction is committed: those of the base
+    /// version, with the staged changes applied.
+    /// Unlike the records of the staging version, this doesn't depend on the API having
+    /// correctly stored what we sent it, so this is what the verification compares the active
+    /// zone with.
+    pub fn expected_records(&self) -> Result<Vec<Record>, Error> {
+        let origin = self.domain.origin();
+        let mut records = self.base_records.clone();
+        for change in &self.changes {
+            change.apply_to(&mut records, &origin)?;
+        }
+        Ok(records)
+    }
+
+    /// Start over from the currently active version: the staging version is replaced by a copy
+    /// of the active one, on which the changes staged so far are applied again.
+    pub fn rebase(&mut self) -> Result<(), Error> {
+        let base = self.domain.get_current_version()?;
+        let records = self.domain.get_zone_records(&base)?;
+        self.domain.delete_version(&self.staging)?;
+        self.base = base;
+        self.staging = self.domain.add_version(&self.version_name)?;
+        self.domain.set_zone_entries(&self.staging, &records)?;
+        self.base_records = records;
+        for change in &self.changes {
+            self.domain.apply_change(&self.staging, change)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure the active version is still the one the transaction is based on, rebasing
+    /// the transaction if allowed to.
+    fn check_base(&mut self) -> Result<(), Error> {
+        let mut rebases = 0;
+        loop {
+            let active = self.domain.get_current_version()?;
+            if active.uuid == self.base.uuid {
+                return Ok(());
+            }
+            if self.on_conflict == OnConflict::Fail || rebases == MAX_REBASES {
+                return Err(Error::ConcurrentModification {
+                    base: self.base.uuid.clone(),
+                    active: active.uuid,
+                });
+            }
+            self.rebase()?;
+            rebases += 1;
+        }
+    }
+
+    /// Enable the staging version, and return it.
+    pub fn commit(mut self) -> Result<Version, Error> {
+        self.check_base()?;
+        if self.verify {
+            let expected = self.expected_records()?;
+            self.domain
+                .enable_version_verified(&self.staging, &expected, &self.base)?;
+        } else {
+            self.domain.enable_version(&self.staging)?;
+        }
+        self.done = true;
+        Ok(self.staging.clone())
+    }
+
+    /// Abandon the transaction, deleting the staging version.
+    /// This is what dropping the transaction does, except errors are reported.
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.done = true;
+        self.domain.delete_version(&self.staging)
+    }
+}
+
+impl<'d, 'a> Drop for ZoneTransaction<'d, 'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            // There is no way to report an error here, use rollback() if you care about it
+            let _ = self.domain.delete_version(&self.staging);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change;
+    use crate::api::Record;
+    use crate::error::Error;
+    use crate::name::DomainName;
+
+    #[test]
+    fn apply_to_local_records() {
+        let origin = DomainName::new("example.com");
+        let mut records = vec![
+            Record::new("www", "A", "192.0.2.1", 3600),
+            Record::new("old", "A", "192.0.2.2", 3600),
+        ];
+        let changes = vec![
+            Change::Add(Record::new("new", "A", "192.0.2.3", 3600)),
+            // records are looked up by identity, whatever the form of their name
+            Change::Delete(Record::new("old.example.com.", "A", "192.0.2.2", 300)),
+            Change::Update {
+                record: Record::new("www", "A", "192.0.2.1", 3600),
+                new_value: "192.0.2.4".into(),
+            },
+        ];
+        for change in &changes {
+            change.apply_to(&mut records, &origin).unwrap();
+        }
+        assert_eq!(
+            records,
+            vec![
+                Record::new("www", "A", "192.0.2.4", 3600),
+                Record::new("new", "A", "192.0.2.3", 3600),
+            ]
+        );
+
+        let missing = Change::Delete(Record::new("old", "A", "192.0.2.2", 3600));
+        assert!(matches!(
+            missing.apply_to(&mut records, &origin),
+            Err(Error::NoRecord)
+        ));
+    }
+}
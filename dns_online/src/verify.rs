@@ -0,0 +1,61 @@
+use crate::api::{Domain, Record, Version};
+use crate::diff::ZoneDiff;
+use crate::error::{Error, VerificationError};
+
+impl<'a> Domain<'a> {
+    /// Enable 'version', then make sure the active zone actually holds the 'expected' records.
+    /// On mismatch, 'previous' is enabled again and Error::VerificationFailed describes the
+    /// discrepancy.
+    /// 'expected' must be what the version is meant to hold (e.g. the records of 'previous'
+    /// with the changes applied, see ZoneTransaction::expected_records), not what was read
+    /// back from 'version', or the records lost when building it would go unnoticed.
+    pub fn enable_version_verified(
+        &self,
+        version: &Version,
+        expected: &[Record],
+        previous: &Version,
+    ) -> Result<(), Error> {
+        self.enable_version(version)?;
+        match self.check_active(version, expected)? {
+            None => Ok(()),
+            Some(mut e) => {
+                e.rolled_back = self.enable_version(previous).is_ok();
+                Err(Error::VerificationFailed(Box::new(e)))
+            }
+        }
+    }
+
+    /// Make sure 'version', edited in place, is still active and holds the 'expected' records.
+    /// There is no previous version to go back to, so the error only describes the discrepancy.
+    pub(crate) fn verify_in_place(
+        &self,
+        version: &Version,
+        expected: &[Record],
+    ) -> Result<(), Error> {
+        match self.check_active(version, expected)? {
+            None => Ok(()),
+            Some(e) => Err(Error::VerificationFailed(Box::new(e))),
+        }
+    }
+
+    /// Compare the active zone with 'version' and the 'expected' records, and describe the
+    /// discrepancy if any.
+    fn check_active(
+        &self,
+        version: &Version,
+        expected: &[Record],
+    ) -> Result<Option<VerificationError>, Error> {
+        let active = self.get_current_version()?;
+        let active_records = self.get_zone_records(&active)?;
+        let diff = ZoneDiff::compute(expected, &active_records, &self.origin());
+        if active.uuid == version.uuid && diff.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(VerificationError {
+            expected_version: version.uuid.clone(),
+            active_version: active.uuid,
+            diff,
+            rolled_back: false,
+        }))
+    }
+}
@@ -0,0 +1,756 @@
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+
+use crate::api::Record;
+use crate::bind::{quote_txt, DEFAULT_TTL};
+use crate::error::{Error, ParseError};
+use crate::name::DomainName;
+use crate::net::DNSType;
+
+/// How deep $INCLUDE directives may be nested, to stop include loops.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A word of a zone file, with its position (1-based).
+struct Token {
+    text: String,
+    quoted: bool,
+    line: usize,
+    column: usize,
+}
+
+/// A logical line of a zone file: a directive or a record, possibly spread over several lines
+/// with parentheses.
+struct Entry {
+    tokens: Vec<Token>,
+    /// The line starts with a blank, so the record belongs to the previous owner
+    owner_omitted: bool,
+}
+
+/// The state of the parser, shared with the included files.
+struct Parser<'z> {
+    zone: &'z DomainName,
+    file: Option<PathBuf>,
+    origin: DomainName,
+    default_ttl: Option<usize>,
+    last_ttl: Option<usize>,
+    last_owner: Option<DomainName>,
+    depth: usize,
+}
+
+fn error_at(file: &Option<PathBuf>, line: usize, column: usize, message: String) -> Error {
+    Error::ZoneParseError(ParseError {
+        file: file.clone(),
+        line,
+        column,
+        message,
+    })
+}
+
+/// Split 'text' into entries, handling comments, quoted strings, escapes and parentheses.
+fn tokenize(text: &str, file: &Option<PathBuf>) -> Result<Vec<Entry>, Error> {
+    let mut entries = Vec::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut owner_omitted = false;
+    // the position of the innermost open parenthesis, if any
+    let mut parens: Vec<(usize, usize)> = Vec::new();
+    let mut line = 1;
+    let mut column = 0;
+    let mut line_start = true;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        column += 1;
+        if line_start && parens.is_empty() && tokens.is_empty() {
+            owner_omitted = c == ' ' || c == '\t';
+        }
+        line_start = false;
+        match c {
+            '\n' => {
+                if parens.is_empty() && !tokens.is_empty() {
+                    entries.push(Entry {
+                        tokens: std::mem::take(&mut tokens),
+                        owner_omitted,
+                    });
+                }
+                line += 1;
+                column = 0;
+                line_start = true;
+            }
+            ';' => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            '(' => parens.push((line, column)),
+            ')' => {
+                if parens.pop().is_none() {
+                    return Err(error_at(file, line, column, "unbalanced ')'".into()));
+                }
+            }
+            c if c.is_whitespace() => {}
+            '"' => {
+                let (start_line, start_column) = (line, column);
+                let mut value = String::new();
+                loop {
+                    column += 1;
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            column += 1;
+                            value.push(unescape(&mut chars, &mut column));
+                        }
+                        Some('\n') | None => {
+                            return Err(error_at(
+                                file,
+                                start_line,
+                                start_column,
+                                "unterminated string".into(),
+                            ));
+                        }
+                        Some(c) => value.push(c),
+                    }
+                }
+                tokens.push(Token {
+                    text: value,
+                    quoted: true,
+                    line: start_line,
+                    column: start_column,
+                });
+            }
+            c => {
+                let start_column = column;
+                let mut value = String::new();
+                let mut next = Some(c);
+                while let Some(c) = next {
+                    if c == '\\' {
+                        column += 1;
+                        value.push(unescape(&mut chars, &mut column));
+                    } else {
+                        value.push(c);
+                    }
+                    next = match chars.peek() {
+                        Some(&c) if !c.is_whitespace() && !matches!(c, ';' | '(' | ')' | '"') => {
+                            column += 1;
+                            chars.next()
+                        }
+                        _ => None,
+                    };
+                }
+                tokens.push(Token {
+                    text: value,
+                    quoted: false,
+                    line,
+                    column: start_column,
+                });
+            }
+        }
+    }
+
+    if let Some((line, column)) = parens.pop() {
+        return Err(error_at(file, line, column, "unclosed '('".into()));
+    }
+    if !tokens.is_empty() {
+        entries.push(Entry {
+            tokens,
+            owner_omitted,
+        });
+    }
+    Ok(entries)
+}
+
+/// Decode the character following a backslash: either "\DDD" (a decimal byte value) or the
+/// character itself.
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>, column: &mut usize) -> char {
+    let first = match chars.next() {
+        Some(c) => c,
+        None => return '\\',
+    };
+    if !first.is_ascii_digit() {
+        return first;
+    }
+    let mut value = first.to_digit(10).unwrap();
+    for _ in 0..2 {
+        match chars.peek().and_then(|c| c.to_digit(10)) {
+            Some(d) => {
+                value = value * 10 + d;
+                chars.next();
+                *column += 1;
+            }
+            None => break,
+        }
+    }
+    std::char::from_u32(value).unwrap_or('?')
+}
+
+/// Parse a TTL, either in seconds or with BIND-style units (e.g. "1h30m", "2d", "1W").
+fn parse_ttl(ttl: &str) -> Option<usize> {
+    if let Ok(ttl) = ttl.parse() {
+        return Some(ttl);
+    }
+    let mut total = 0;
+    let mut value: Option<usize> = None;
+    for c in ttl.chars() {
+        if let Some(d) = c.to_digit(10) {
+            value = Some(
+                value
+                    .unwrap_or(0)
+                    .checked_mul(10)?
+                    .checked_add(d as usize)?,
+            );
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 7 * 86400,
+            _ => return None,
+        };
+        total = value.take()?.checked_mul(unit)?.checked_add(total)?;
+    }
+    if value.is_some() {
+        return None;
+    }
+    Some(total)
+}
+
+/// Parse a record type, ignoring the case.
+pub(crate) fn parse_type(record_type: &str) -> Option<DNSType> {
+    Some(match record_type.to_ascii_uppercase().as_str() {
+        "A" => DNSType::A,
+        "AAAA" => DNSType::AAAA,
+        "TXT" => DNSType::TXT,
+        "CNAME" => DNSType::CNAME,
+        "MX" => DNSType::MX,
+        "NS" => DNSType::NS,
+        "CAA" => DNSType::CAA,
+        "SRV" => DNSType::SRV,
+        _ => return None,
+    })
+}
+
+impl<'z> Parser<'z> {
+    fn error(&self, token: &Token, message: String) -> Error {
+        error_at(&self.file, token.line, token.column, message)
+    }
+
+    /// Resolve a name against the current origin.
+    fn name(&self, token: &Token) -> Result<DomainName, Error> {
+        if token.text == "@" {
+            return Ok(self.origin.clone());
+        }
+        let name = DomainName::parse(&token.text)
+            .map_err(|_| self.error(token, format!("invalid name '{}'", token.text)))?;
+        if token.text.ends_with('.') {
+            Ok(name)
+        } else {
+            Ok(name.join(&self.origin))
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self, token: &Token, what: &str) -> Result<T, Error> {
+        token
+            .text
+            .parse()
+            .map_err(|_| self.error(token, format!("invalid {} '{}'", what, token.text)))
+    }
+
+    fn parse(&mut self, text: &str, records: &mut Vec<Record>) -> Result<(), Error> {
+        for entry in tokenize(text, &self.file)? {
+            let first = &entry.tokens[0];
+            if !entry.owner_omitted && first.text.starts_with('$') && !first.quoted {
+                self.directive(&entry.tokens, records)?;
+            } else if let Some(record) = self.record(&entry)? {
+                records.push(record);
+            }
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, tokens: &[Token], records: &mut Vec<Record>) -> Result<(), Error> {
+        let directive = &tokens[0];
+        let argument = match tokens.get(1) {
+            Some(argument) => argument,
+            None => {
+                return Err(self.error(directive, format!("missing argument to {}", directive.text)))
+            }
+        };
+        match directive.text.to_ascii_uppercase().as_str() {
+            "$ORIGIN" => self.origin = self.name(argument)?,
+            "$TTL" => {
+                let ttl = parse_ttl(&argument.text).ok_or_else(|| {
+                    self.error(argument, format!("invalid ttl '{}'", argument.text))
+                })?;
+                self.default_ttl = Some(ttl);
+            }
+            "$INCLUDE" => {
+                if self.depth == MAX_INCLUDE_DEPTH {
+                    return Err(self.error(directive, "too many nested $INCLUDE".into()));
+                }
+                let mut path = PathBuf::from(&argument.text);
+                if path.is_relative() {
+                    if let Some(dir) = self.file.as_ref().and_then(|f| f.parent()) {
+                        path = dir.join(path);
+                    }
+                }
+                let text = fs::read_to_string(&path).map_err(|e| {
+                    self.error(argument, format!("cannot read '{}': {}", path.display(), e))
+                })?;
+                // The included file may change the origin and the ttl, but only for itself
+                let mut included = Parser {
+                    zone: self.zone,
+                    file: Some(path),
+                    origin: match tokens.get(2) {
+                        Some(origin) => self.name(origin)?,
+                        None => self.origin.clone(),
+                    },
+                    default_ttl: self.default_ttl,
+                    last_ttl: self.last_ttl,
+                    last_owner: None,
+                    depth: self.depth + 1,
+                };
+                included.parse(&text, records)?;
+            }
+            _ => {
+                return Err(self.error(directive, format!("unknown directive '{}'", directive.text)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a record, returning None for the SOA records, which are managed by online.net.
+    fn record(&mut self, entry: &Entry) -> Result<Option<Record>, Error> {
+        let mut tokens = entry.tokens.iter().peekable();
+
+        let owner = if entry.owner_omitted {
+            match &self.last_owner {
+                Some(owner) => owner.clone(),
+                None => return Err(self.error(&entry.tokens[0], "no previous owner name".into())),
+            }
+        } else {
+            self.name(tokens.next().unwrap())?
+        };
+        self.last_owner = Some(owner.clone());
+
+        // the ttl and the class are both optional, and may come in any order
+        let mut ttl = None;
+        while let Some(token) = tokens.peek() {
+            if token.text.eq_ignore_ascii_case("IN") {
+                tokens.next();
+            } else if ["CH", "HS", "CS"]
+                .iter()
+                .any(|class| token.text.eq_ignore_ascii_case(class))
+            {
+                return Err(self.error(token, format!("unsupported class '{}'", token.text)));
+            } else if ttl.is_none() && token.text.starts_with(|c: char| c.is_ascii_digit()) {
+                ttl =
+                    Some(parse_ttl(&token.text).ok_or_else(|| {
+                        self.error(token, format!("invalid ttl '{}'", token.text))
+                    })?);
+                tokens.next();
+            } else {
+                break;
+            }
+        }
+        if ttl.is_some() {
+            self.last_ttl = ttl;
+        }
+        let ttl = ttl
+            .or(self.default_ttl)
+            .or(self.last_ttl)
+            .unwrap_or(DEFAULT_TTL);
+
+        let type_token = match tokens.next() {
+            Some(token) => token,
+            None => {
+                let last = entry.tokens.last().unwrap();
+                return Err(self.error(last, "missing record type".into()));
+            }
+        };
+        if type_token.text.eq_ignore_ascii_case("SOA") {
+            return Ok(None);
+        }
+        let record_type = parse_type(&type_token.text).ok_or_else(|| {
+            self.error(
+                type_token,
+                format!("unsupported record type '{}'", type_token.text),
+            )
+        })?;
+
+        let data: Vec<&Token> = tokens.collect();
+        let expected = match record_type {
+            DNSType::A | DNSType::AAAA | DNSType::CNAME | DNSType::NS => Some(1),
+            DNSType::MX => Some(2),
+            DNSType::CAA => Some(3),
+            DNSType::SRV => Some(4),
+            DNSType::TXT => None,
+        };
+        if data.is_empty() || expected.is_some_and(|n| n != data.len()) {
+            return Err(self.error(
+                type_token,
+                format!("wrong number of fields for a {} record", type_token.text),
+            ));
+        }
+
+        let target = |token: &Token| self.name(token).map(|name| name.to_fqdn());
+        let mut priority = None;
+        let value = match record_type {
+            DNSType::A => {
+                self.number::<Ipv4Addr>(data[0], "IPv4 address")?;
+                data[0].text.clone()
+            }
+            DNSType::AAAA => {
+                self.number::<Ipv6Addr>(data[0], "IPv6 address")?;
+                data[0].text.clone()
+            }
+            DNSType::CNAME | DNSType::NS => target(data[0])?,
+            DNSType::MX => {
+                priority = Some(self.number::<u16>(data[0], "preference")? as usize);
+                target(data[1])?
+            }
+            DNSType::SRV => {
+                priority = Some(self.number::<u16>(data[0], "priority")? as usize);
+                let weight: u16 = self.number(data[1], "weight")?;
+                let port: u16 = self.number(data[2], "port")?;
+                format!("{} {} {}", weight, port, target(data[3])?)
+            }
+            DNSType::CAA => {
+                let flags: u8 = self.number(data[0], "flags")?;
+                format!(
+                    "{} {} \"{}\"",
+                    flags,
+                    data[1].text,
+                    data[2].text.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            }
+            // quoted, the way the API returns them
+            DNSType::TXT => quote_txt(&data.iter().map(|t| t.text.as_str()).collect::<String>()),
+        };
+
+        let name = match owner.relative_to(self.zone) {
+            Some("") => "@".to_string(),
+            Some(name) => name.to_string(),
+            None => {
+                let token = if entry.owner_omitted {
+                    type_token
+                } else {
+                    &entry.tokens[0]
+                };
+                return Err(self.error(
+                    token,
+                    format!("'{}' is outside of the zone {}", owner, self.zone),
+                ));
+            }
+        };
+
+        let mut record = Record::new(name, record_type, value, ttl);
+        record.priority = priority;
+        Ok(Some(record))
+    }
+}
+
+fn parse_zone(
+    text: &str,
+    origin: &DomainName,
+    file: Option<PathBuf>,
+) -> Result<Vec<Record>, Error> {
+    let mut parser = Parser {
+        zone: origin,
+        file,
+        origin: origin.clone(),
+        default_ttl: None,
+        last_ttl: None,
+        last_owner: None,
+        depth: 0,
+    };
+    let mut records = Vec::new();
+    parser.parse(text, &mut records)?;
+    Ok(records)
+}
+
+/// Parse the zone file 'text' of the zone 'origin', e.g. to migrate a zone hosted elsewhere.
+/// The $ORIGIN, $TTL and $INCLUDE directives, parentheses, comments, "@" and omitted owners are
+/// supported. SOA records are skipped (online.net manages them), and the names in the data of
+/// the records are made absolute.
+/// The records are returned with names relative to 'origin', ready to be passed to
+/// Domain::set_zone_entries or Domain::sync. Included files are looked up relative to the
+/// current directory.
+pub fn parse(text: &str, origin: &DomainName) -> Result<Vec<Record>, Error> {
+    parse_zone(text, origin, None)
+}
+
+/// Same as parse, reading the zone file at 'path'. Included files are looked up relative to the
+/// file including them.
+pub fn parse_file(path: &Path, origin: &DomainName) -> Result<Vec<Record>, Error> {
+    let text = fs::read_to_string(path)?;
+    parse_zone(&text, origin, Some(path.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bind::{to_bind_with, BindStyle};
+    use crate::set::canonical_order;
+
+    fn origin() -> DomainName {
+        DomainName::new("example.com")
+    }
+
+    fn parse_ok(text: &str) -> Vec<Record> {
+        parse(text, &origin()).unwrap()
+    }
+
+    /// The name, ttl and data of each record.
+    fn summary(records: &[Record]) -> Vec<(&str, usize, &str)> {
+        records
+            .iter()
+            .map(|r| (r.name.as_str(), r.ttl, r.data.as_str()))
+            .collect()
+    }
+
+    fn parse_error(text: &str) -> ParseError {
+        match parse(text, &origin()) {
+            Err(Error::ZoneParseError(e)) => e,
+            res => panic!(
+                "expected a parse error, got {:?}",
+                res.map(|r| summary(&r).len())
+            ),
+        }
+    }
+
+    /// A fresh directory for the files of the test 'name'.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zonefile-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn origin_and_ttl() {
+        let records = parse_ok(
+            "$ORIGIN example.com.
+$TTL 1h
+@ IN A 192.0.2.1
+www 300 IN A 192.0.2.2
+$ORIGIN sub.example.com.
+host A 192.0.2.3
+$ttl 2d
+other IN 1W A 192.0.2.4 ; the class may come before the ttl
+last A 192.0.2.5
+",
+        );
+        assert_eq!(
+            summary(&records),
+            vec![
+                ("@", 3600, "192.0.2.1"),
+                ("www", 300, "192.0.2.2"),
+                ("host.sub", 3600, "192.0.2.3"),
+                ("other.sub", 604800, "192.0.2.4"),
+                ("last.sub", 172800, "192.0.2.5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ttl_without_directive() {
+        let records = parse_ok("a A 192.0.2.1\nb 600 A 192.0.2.2\nc A 192.0.2.3\n");
+        assert_eq!(
+            summary(&records),
+            vec![
+                ("a", DEFAULT_TTL, "192.0.2.1"),
+                ("b", 600, "192.0.2.2"),
+                ("c", 600, "192.0.2.3"),
+            ]
+        );
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1x"), None);
+        assert_eq!(parse_ttl("1h30"), None);
+        assert_eq!(parse_ttl("99999999999999999999999w"), None);
+        assert_eq!(parse_ttl(&format!("{}w", usize::MAX / 2)), None);
+        assert_eq!(parse_ttl(&format!("{}s1s", usize::MAX)), None);
+    }
+
+    #[test]
+    fn include() {
+        let dir = test_dir("include");
+        fs::write(
+            dir.join("main.zone"),
+            "$TTL 300
+@ A 192.0.2.1
+$INCLUDE hosts.zone
+$INCLUDE hosts.zone lab
+after A 192.0.2.9
+",
+        )
+        .unwrap();
+        fs::write(dir.join("hosts.zone"), "$TTL 60\nhost A 192.0.2.2\n").unwrap();
+
+        let records = parse_file(&dir.join("main.zone"), &origin()).unwrap();
+        // the origin and the ttl of an included file only apply to it
+        assert_eq!(
+            summary(&records),
+            vec![
+                ("@", 300, "192.0.2.1"),
+                ("host", 60, "192.0.2.2"),
+                ("host.lab", 60, "192.0.2.2"),
+                ("after", 300, "192.0.2.9"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_loop() {
+        let dir = test_dir("include-loop");
+        let path = dir.join("loop.zone");
+        fs::write(&path, "a A 192.0.2.1\n$INCLUDE loop.zone\n").unwrap();
+        match parse_file(&path, &origin()) {
+            Err(Error::ZoneParseError(e)) => {
+                assert_eq!(e.file, Some(path));
+                assert_eq!((e.line, e.column), (2, 1));
+                assert!(e.message.contains("$INCLUDE"), "{}", e.message);
+            }
+            _ => panic!("expected a parse error"),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parentheses_and_comments() {
+        let records = parse_ok(
+            "@ IN SOA ns1 hostmaster (
+        2024010101 ; serial
+        3600 900 604800 300 )
+@ 300 IN MX ( 10 ; preference
+              mail )
+txt TXT \"a;b\" ; not a comment inside the string
+",
+        );
+        // the SOA record is skipped
+        assert_eq!(
+            summary(&records),
+            vec![("@", 300, "mail.example.com."), ("txt", 300, "\"a;b\"")]
+        );
+        assert_eq!(records[0].priority, Some(10));
+    }
+
+    #[test]
+    fn omitted_owner() {
+        let records = parse_ok(
+            "www 300 A 192.0.2.1
+    600 AAAA 2001:db8::1
+\tTXT \"hello\"
+",
+        );
+        assert_eq!(
+            summary(&records),
+            vec![
+                ("www", 300, "192.0.2.1"),
+                ("www", 600, "2001:db8::1"),
+                ("www", 600, "\"hello\""),
+            ]
+        );
+
+        let e = parse_error("  A 192.0.2.1\n");
+        assert_eq!((e.line, e.column), (1, 3));
+    }
+
+    #[test]
+    fn escapes() {
+        let records = parse_ok("txt TXT \"a\\059b\" \"say \\\"hi\\\"\" \\065BC\n");
+        assert_eq!(records[0].data, "\"a;bsay \\\"hi\\\"ABC\"");
+    }
+
+    #[test]
+    fn txt_quoted_like_the_api() {
+        let spf = "\"v=spf1 include:\\\"x\\\" -all\"";
+        let records = parse_ok(&format!("@ TXT {}\nlong TXT {}\n", spf, "a".repeat(300)));
+        assert_eq!(records[0].data, spf);
+        assert_eq!(
+            records[1].data,
+            format!("\"{}\" \"{}\"", "a".repeat(255), "a".repeat(45))
+        );
+    }
+
+    #[test]
+    fn names_and_targets() {
+        let records = parse_ok(
+            "www.example.com. CNAME target.example.net.
+ftp CNAME www
+_sip._tcp SRV 10 5 5060 sip
+",
+        );
+        assert_eq!(
+            summary(&records),
+            vec![
+                ("www", DEFAULT_TTL, "target.example.net."),
+                ("ftp", DEFAULT_TTL, "www.example.com."),
+                ("_sip._tcp", DEFAULT_TTL, "5 5060 sip.example.com."),
+            ]
+        );
+        assert_eq!(records[2].priority, Some(10));
+    }
+
+    #[test]
+    fn error_positions() {
+        let e = parse_error("ok A 192.0.2.1\nbad A 192.0.2.300\n");
+        assert_eq!((e.line, e.column), (2, 7));
+        assert!(e.message.contains("IPv4"), "{}", e.message);
+
+        let e = parse_error("www A (192.0.2.1\n");
+        assert_eq!((e.line, e.column), (1, 7));
+
+        let e = parse_error("www A 192.0.2.1 )\n");
+        assert_eq!((e.line, e.column), (1, 17));
+
+        let e = parse_error("\ntxt TXT \"abc\n");
+        assert_eq!((e.line, e.column), (2, 9));
+
+        let e = parse_error("www FOO bar\n");
+        assert_eq!((e.line, e.column), (1, 5));
+
+        let e = parse_error("www MX mail\n");
+        assert_eq!((e.line, e.column), (1, 5));
+
+        let e = parse_error("www.example.net. A 192.0.2.1\n");
+        assert_eq!((e.line, e.column), (1, 1));
+
+        let e = parse_error("$FOO bar\n");
+        assert_eq!((e.line, e.column), (1, 1));
+
+        let e = parse_error("www 300 CH A 192.0.2.1\n");
+        assert_eq!((e.line, e.column), (1, 9));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut records = vec![
+            Record::new("@", "A", "192.0.2.1", 3600),
+            Record::new("www", "CNAME", "target.example.com", 3600),
+            Record::new("@", "MX", "10 mail.example.com.", 300),
+            Record::new("@", "TXT", "\"v=spf1 include:\\\"x\\\" -all\"", 3600),
+            Record::new("long", "TXT", "a".repeat(300), 3600),
+            Record::new("@", "CAA", "0 issue \"letsencrypt.org\"", 3600),
+            Record::new("_sip._tcp", "SRV", "5 5060 sip", 60),
+        ];
+        records[6].priority = Some(10);
+        for style in &[BindStyle::default(), BindStyle::canonical()] {
+            let zone = to_bind_with(&records, &origin(), style);
+            let parsed = parse(&zone, &origin()).unwrap();
+            assert_eq!(parsed.len(), records.len(), "{}", zone);
+            for (r, p) in canonical_order(&records, &origin())
+                .iter()
+                .zip(canonical_order(&parsed, &origin()).iter())
+            {
+                assert!(r.same_as(p, &origin()), "{:?} != {:?}", r, p);
+                assert_eq!(r.ttl, p.ttl);
+            }
+        }
+    }
+}
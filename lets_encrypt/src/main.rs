@@ -1,23 +1,25 @@
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgGroup, SubCommand};
+use std::path::Path;
+use std::time::Duration;
 
+use dns_online::name::{self, DomainName};
 use dns_online::*;
 
 fn find_entry(
-    records: &Vec<Record>,
-    name: &str,
-    short_name: &str,
+    records: &[Record],
+    origin: &DomainName,
+    name: &DomainName,
     ty: net::DNSType,
     value: Option<&str>,
 ) -> Option<Record> {
     for record in records {
-        if record.record_type != ty || (record.name != name && record.name != short_name) {
+        if record.record_type != ty || record.domain_name(origin) != *name {
             continue;
         }
 
         if let Some(txt) = value {
-            // compare with and without the quotes
-            if txt == &record.data
-                || (record.data.len() > 2 && txt == &record.data[1..record.data.len() - 1])
+            // compare the normalised values, e.g. with and without the quotes
+            if set::normalise_data(ty, txt, origin) == set::normalise_data(ty, &record.data, origin)
             {
                 return Some(record.clone());
             }
@@ -31,13 +33,56 @@ fn find_entry(
 fn find_entry_in_version(
     domain: &Domain,
     version: &Version,
-    name: &str,
-    short_name: &str,
+    name: &DomainName,
     ty: net::DNSType,
     value: Option<&str>,
 ) -> Option<Record> {
-    let zone_entries: Vec<Record> = domain.get_zone_records(&version).unwrap();
-    find_entry(&zone_entries, name, short_name, ty, value)
+    let zone_entries: Vec<Record> = domain.get_zone_records(version).unwrap();
+    find_entry(&zone_entries, &domain.origin(), name, ty, value)
+}
+
+fn display_name(name: &DomainName, unicode: bool) -> String {
+    if unicode {
+        format!("{}.", name.to_unicode())
+    } else {
+        name.to_fqdn()
+    }
+}
+
+/// Parse durations like "90s", "30m", "12h", "7d" or "2w".
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let unit_pos = duration.find(|c: char| !c.is_ascii_digit())?;
+    let value: u64 = duration[0..unit_pos].parse().ok()?;
+    let unit = match &duration[unit_pos..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return None,
+    };
+    Some(Duration::from_secs(value * unit))
+}
+
+/// Print the result of lint, and exit with an error if the zone is broken.
+fn report_findings(res: Result<Vec<lint::Finding>, error::Error>) {
+    let findings = match res {
+        Ok(findings) => findings,
+        Err(e) => {
+            eprintln!("Couldn't check the zone: {:?}", e);
+            return;
+        }
+    };
+
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    if findings.is_empty() {
+        println!("No problem found.");
+    }
+    if findings.iter().any(|f| f.severity == lint::Severity::Error) {
+        std::process::exit(1);
+    }
 }
 
 fn main() {
@@ -52,16 +97,36 @@ fn main() {
                 .short("a")
                 .long("api-key")
                 .takes_value(true)
-                .required(true),
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("API key of an account (repeat it to work on the domains of several accounts)"),
         )
         .arg(
             Arg::with_name("Record")
                 .short("n")
                 .long("name")
                 .takes_value(true)
-                .required(true),
+                .help("Name of the record (mandatory unless the subcommand works on the whole account)"),
         )
         .arg(Arg::with_name("Value").long("value").takes_value(true))
+        .arg(
+            Arg::with_name("Verify")
+                .long("verify")
+                .help("Check the zone once modified, and go back to the previous version on mismatch"),
+        )
+        .arg(
+            Arg::with_name("Lock directory")
+                .long("lock-dir")
+                .takes_value(true)
+                .help("Directory holding the lock files used to serialise concurrent runs"),
+        )
+        .arg(
+            Arg::with_name("Lock timeout")
+                .long("lock-timeout")
+                .takes_value(true)
+                .help("How long to wait for another run working on the same domain (e.g. 90s, 5m)"),
+        )
         .arg(
             Arg::with_name("Entry type")
                 .short("t")
@@ -99,29 +164,289 @@ fn main() {
                         .required(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List the entries of the DNS zone matching the name (and the type, if specified)")
+                .arg(
+                    Arg::with_name("Unicode")
+                        .long("unicode")
+                        .help("Display internationalized names in their Unicode form"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Print the DNS zone containing the name")
+                .arg(
+                    Arg::with_name("Unicode")
+                        .long("unicode")
+                        .help("Display internationalized names in their Unicode form (bind format only)"),
+                )
+                .arg(
+                    Arg::with_name("Format")
+                        .long("format")
+                        .possible_values(format::FORMATS)
+                        .default_value("bind"),
+                )
+                .arg(
+                    Arg::with_name("Canonical")
+                        .long("canonical")
+                        .help("Sort (and align) the records, so that the same zone always gives the same output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Create a new version of the DNS zone containing the name from a file")
+                .arg(
+                    Arg::with_name("File")
+                        .long("file")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("Format")
+                        .long("format")
+                        .possible_values(format::FORMATS)
+                        .default_value("bind"),
+                )
+                .arg(
+                    Arg::with_name("Version Name")
+                        .long("version-name")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("Enable")
+                        .long("enable")
+                        .help("Enable the imported version"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Look for common mistakes in the DNS zone containing the name, or in a file")
+                .arg(
+                    Arg::with_name("UUID")
+                        .long("uuid")
+                        .takes_value(true)
+                        .help("Version to check (defaults to the active one)"),
+                )
+                .arg(
+                    Arg::with_name("File")
+                        .long("file")
+                        .takes_value(true)
+                        .conflicts_with("UUID")
+                        .help("Check this file (a zone whose origin is the name) instead of a version of the zone"),
+                )
+                .arg(
+                    Arg::with_name("Format")
+                        .long("format")
+                        .possible_values(format::FORMATS)
+                        .default_value("bind"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("prune")
+                .about("Delete the old versions of the DNS zone containing the name")
+                .arg(
+                    Arg::with_name("Keep")
+                        .long("keep")
+                        .takes_value(true)
+                        .help("Number of recent versions to keep"),
+                )
+                .arg(
+                    Arg::with_name("Prefix")
+                        .long("prefix")
+                        .takes_value(true)
+                        .help("Only delete the versions whose name starts with this prefix"),
+                )
+                .arg(
+                    Arg::with_name("Regex")
+                        .long("regex")
+                        .takes_value(true)
+                        .help("Only delete the versions whose name matches this regex"),
+                )
+                .arg(
+                    Arg::with_name("Older than")
+                        .long("older-than")
+                        .takes_value(true)
+                        .help("Only delete the versions older than this (e.g. 12h, 30d)"),
+                )
+                .arg(
+                    Arg::with_name("Dry run")
+                        .long("dry-run")
+                        .help("List the versions that would be deleted, without deleting them"),
+                )
+                // without any criterion, every inactive version would be deleted
+                .group(
+                    ArgGroup::with_name("Criteria")
+                        .args(&["Keep", "Prefix", "Regex", "Older than"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("domains")
+                .about("List the domains of the account, marking those hosted outside of online.net"),
+        )
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about("Save every version of every domain of the account in a directory")
+                .arg(
+                    Arg::with_name("Directory")
+                        .long("dir")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Recreate a version of the DNS zone containing the name from a snapshot")
+                .arg(
+                    Arg::with_name("Directory")
+                        .long("dir")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("Version Name")
+                        .long("version-name")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("UUID")
+                        .long("uuid")
+                        .takes_value(true)
+                        .help("Version to restore (defaults to the one active when the snapshot was taken)"),
+                )
+                .arg(
+                    Arg::with_name("Enable")
+                        .long("enable")
+                        .help("Enable the restored version"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dnssec")
+                .about("Manage the DNSSEC signing of the domain containing the name")
+                .arg(
+                    Arg::with_name("Action")
+                        .possible_values(&["status", "enable", "disable", "ds"])
+                        .required(true)
+                        .help("Show the status, enable or disable DNSSEC, or print the records to publish at the registrar"),
+                ),
+        )
         .get_matches();
 
-    let api_key = matches.value_of("API key").unwrap();
+    let api_keys: Vec<&str> = matches.values_of("API key").unwrap().collect();
+
+    // subcommands working on the whole accounts
+    if let ("domains", Some(_)) = matches.subcommand() {
+        match accounts::Accounts::query(&api_keys) {
+            Ok(accounts) => {
+                for domain in accounts.domains() {
+                    let mut flags = Vec::new();
+                    if domain.external {
+                        flags.push("external");
+                    }
+                    if domain.dnssec {
+                        flags.push("dnssec");
+                    }
+                    if flags.is_empty() {
+                        println!("{}", domain.name);
+                    } else {
+                        println!("{} ({})", domain.name, flags.join(", "));
+                    }
+                }
+                for name in accounts.duplicates() {
+                    println!("{} (duplicate, ignored)", name);
+                }
+            }
+            Err(e) => eprintln!("Couldn't list the domains: {:?}", e),
+        }
+        return;
+    }
+    if let ("snapshot", Some(args)) = matches.subcommand() {
+        let directory = Path::new(args.value_of("Directory").unwrap());
+        let res = accounts::Accounts::query(&api_keys)
+            .and_then(|accounts| snapshot::snapshot_domains(accounts.domains(), directory));
+        match res {
+            Ok(manifest) => println!(
+                "{} domains saved in {}.",
+                manifest.domains.len(),
+                directory.display()
+            ),
+            Err(e) => eprintln!("Couldn't take the snapshot: {:?}", e),
+        }
+        return;
+    }
+
     let record = {
-        let mut record = matches.value_of("Record").unwrap().to_owned();
-        if !record.ends_with(".") {
+        let mut record = match matches.value_of("Record") {
+            Some(record) => record.to_owned(),
+            None => {
+                eprintln!("Please specify the name with the --name flag");
+                return;
+            }
+        };
+        if !record.ends_with('.') {
             record.push('.');
         }
-        record
+        match name::to_ascii(&record) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("Invalid record name: {:?}", e);
+                return;
+            }
+        }
     };
     let value = matches.value_of("Value");
+    let verify = matches.is_present("Verify");
+    let mut lock_config = lock::LockConfig::default();
+    if let Some(directory) = matches.value_of("Lock directory") {
+        lock_config.directory = directory.into();
+    }
+    if let Some(timeout) = matches.value_of("Lock timeout") {
+        match parse_duration(timeout) {
+            Some(timeout) => lock_config.timeout = timeout,
+            None => {
+                eprintln!("Invalid lock timeout: {}", timeout);
+                return;
+            }
+        }
+    }
     let record_type = matches.value_of("Entry type").unwrap().into();
 
-    let available_domains = match query_available_domains(&api_key) {
-        Ok(domain) => domain,
+    let name = DomainName::new(&record);
+
+    // checking a file doesn't need the API, the name is the origin of the zone
+    if let ("lint", Some(args)) = matches.subcommand() {
+        if let Some(path) = args.value_of("File") {
+            let zone_format = format::by_name(args.value_of("Format").unwrap()).unwrap();
+            report_findings(lint::lint_file(
+                Path::new(path),
+                zone_format.as_ref(),
+                &name,
+            ));
+            return;
+        }
+    }
+
+    let accounts = match accounts::Accounts::query(&api_keys) {
+        Ok(accounts) => accounts,
         Err(_) => {
-            eprintln!("No domain were found with you api key.");
+            eprintln!("No domain were found with you api keys.");
             return;
         }
     };
-    if let Some((domain, local_part)) = Domain::find_and_extract_path(&record, available_domains) {
-        let version = domain.get_current_version().unwrap();
-
+    // the zones hosted outside of online.net can't be managed through the API
+    let accounts = accounts.skip_external(true);
+    let resolved = match accounts.resolve(&name) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("Couldn't find the domain of {}: {:?}", record, e);
+            return;
+        }
+    };
+    if let Some((domain, _)) = resolved {
         if matches.subcommand_name().is_none() {
             eprintln!("You must specify a subcommand.");
             return;
@@ -129,8 +454,26 @@ fn main() {
 
         let subcommand = matches.subcommand_name().unwrap();
 
-        let old_entry =
-            find_entry_in_version(&domain, &version, &record, local_part, record_type, value);
+        // Serialise the modifications of the domain with the other processes (acme.sh, cron
+        // jobs...) working on it. Single record changes hold the lock from the lookup of the
+        // existing entries to the change itself, so that the lookup can't be outdated.
+        let (_lock, domain) = match subcommand {
+            "add" | "delete" | "update" => {
+                match lock::DomainLock::acquire(&domain.name, &lock_config) {
+                    Ok(lock) => (Some(lock), domain.clone()),
+                    Err(e) => {
+                        eprintln!("Couldn't lock the domain {}: {:?}", domain.name, e);
+                        return;
+                    }
+                }
+            }
+            _ => (None, domain.clone().with_lock(lock_config)),
+        };
+        let domain = &domain;
+
+        let version = domain.get_current_version().unwrap();
+
+        let old_entry = find_entry_in_version(domain, &version, &name, record_type, value);
 
         match subcommand {
             "add" => {
@@ -155,16 +498,19 @@ fn main() {
                     .unwrap()
                     .unwrap();
 
-                let new_version = domain.duplicate_version(&version, &version_name).unwrap();
-
-                domain
-                    .add_record(
-                        &new_version,
-                        &Record::new(record.clone(), record_type, value.unwrap(), 86400),
-                    )
+                // the staging version is cleaned up if anything goes wrong
+                let mut transaction = domain.begin_transaction(version_name).unwrap();
+                transaction.set_verify(verify);
+                transaction.set_on_conflict(transaction::OnConflict::Rebase);
+                transaction
+                    .add(&Record::new(
+                        record.clone(),
+                        record_type,
+                        value.unwrap(),
+                        86400,
+                    ))
                     .unwrap();
-
-                domain.enable_version(&new_version).unwrap();
+                transaction.commit().unwrap();
 
                 println!("The entry {} has been deployed.", record);
             }
@@ -185,13 +531,11 @@ fn main() {
                     .unwrap()
                     .unwrap();
 
-                let new_version = domain.duplicate_version(&version, &version_name).unwrap();
-
-                domain
-                    .delete_record(&new_version, &old_entry.unwrap())
-                    .unwrap();
-
-                domain.enable_version(&new_version).unwrap();
+                let mut transaction = domain.begin_transaction(version_name).unwrap();
+                transaction.set_verify(verify);
+                transaction.set_on_conflict(transaction::OnConflict::Rebase);
+                transaction.delete(&old_entry.unwrap()).unwrap();
+                transaction.commit().unwrap();
 
                 println!("The entry {} has been destroyed.", record);
             }
@@ -212,15 +556,8 @@ fn main() {
                     }
                 };
 
-                if find_entry_in_version(
-                    &domain,
-                    &version,
-                    &record,
-                    local_part,
-                    record_type,
-                    Some(new_value),
-                )
-                .is_some()
+                if find_entry_in_version(domain, &version, &name, record_type, Some(new_value))
+                    .is_some()
                 {
                     println!("The entry is already present in the zone, doing nothing.");
                     return;
@@ -228,14 +565,222 @@ fn main() {
 
                 let record = old_entry.unwrap();
 
-                domain
-                    .update_current_version_record(&record, new_value)
-                    .unwrap();
+                if verify {
+                    let change = transaction::Change::Update {
+                        record: record.clone(),
+                        new_value: new_value.into(),
+                    };
+                    domain
+                        .modify_active_verified(&[change], modify::Strategy::Auto)
+                        .unwrap();
+                } else {
+                    domain
+                        .update_current_version_record(&record, new_value)
+                        .unwrap();
+                }
 
                 println!("The entry {} has been updated.", record.id);
             }
+            "list" => {
+                let unicode = matches
+                    .subcommand_matches(subcommand)
+                    .map(|x| x.is_present("Unicode"))
+                    .unwrap_or(false);
+                let mut query = query::RecordQuery::new().name(name.as_str());
+                if matches.occurrences_of("Entry type") > 0 {
+                    query = query.record_type(record_type);
+                }
+                let origin = domain.origin();
+
+                for r in domain.query(&version, &query).unwrap() {
+                    println!(
+                        "{} {} {} {}",
+                        display_name(&r.domain_name(&origin), unicode),
+                        r.ttl,
+                        String::from(&r.record_type),
+                        r.data
+                    );
+                }
+            }
+            "export" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+                let records = domain.get_zone_records(&version).unwrap();
+                let format_name = args.value_of("Format").unwrap();
+                let canonical = args.is_present("Canonical");
+                if format_name == "bind" && args.is_present("Unicode") {
+                    let style = bind::BindStyle {
+                        unicode: true,
+                        canonical,
+                        align: canonical,
+                    };
+                    print!("{}", bind::to_bind_with(&records, &domain.origin(), &style));
+                    return;
+                }
+                let zone_format = format::by_name(format_name).unwrap();
+                let res = if canonical {
+                    zone_format.write_canonical(&records, &domain.origin())
+                } else {
+                    zone_format.write(&records, &domain.origin())
+                };
+                match res {
+                    Ok(zone) => print!("{}", zone),
+                    Err(e) => eprintln!("Couldn't export the zone: {:?}", e),
+                }
+            }
+            "import" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+                let path = Path::new(args.value_of("File").unwrap());
+                let zone_format = format::by_name(args.value_of("Format").unwrap()).unwrap();
+
+                let records = match zone_format.read_file(path, &domain.origin()) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        eprintln!("Couldn't read {}: {:?}", path.display(), e);
+                        return;
+                    }
+                };
+                match domain.create_version(
+                    args.value_of("Version Name").unwrap(),
+                    &records,
+                    args.is_present("Enable"),
+                ) {
+                    Ok(version) => println!(
+                        "{} records imported in the version {} ({}) of domain {}.",
+                        records.len(),
+                        version.name,
+                        version.uuid,
+                        domain.name
+                    ),
+                    Err(e) => eprintln!("Couldn't import the zone: {:?}", e),
+                }
+            }
+            "lint" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+                let res = if let Some(uuid) = args.value_of("UUID") {
+                    domain
+                        .get_version(uuid)
+                        .and_then(|version| domain.lint_version(&version))
+                } else {
+                    domain.lint_version(&version)
+                };
+                report_findings(res);
+            }
+            "prune" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+
+                let mut policy = prune::PrunePolicy::new();
+                if let Some(keep) = args.value_of("Keep") {
+                    match keep.parse() {
+                        Ok(keep) => policy = policy.keep_latest(keep),
+                        Err(_) => {
+                            eprintln!("Invalid number of versions to keep: {}", keep);
+                            return;
+                        }
+                    }
+                }
+                if let Some(prefix) = args.value_of("Prefix") {
+                    policy = policy.name_prefix(prefix);
+                }
+                if let Some(regex) = args.value_of("Regex") {
+                    policy = match policy.name_regex(regex) {
+                        Ok(policy) => policy,
+                        Err(e) => {
+                            eprintln!("Invalid regex: {:?}", e);
+                            return;
+                        }
+                    };
+                }
+                if let Some(age) = args.value_of("Older than") {
+                    match parse_duration(age) {
+                        Some(age) => policy = policy.older_than(age),
+                        None => {
+                            eprintln!("Invalid duration: {}", age);
+                            return;
+                        }
+                    }
+                }
+
+                if args.is_present("Dry run") {
+                    for v in domain.versions_to_prune(&policy).unwrap() {
+                        println!("{} {}", v.uuid, v.name);
+                    }
+                } else {
+                    let deleted = domain.prune_versions(&policy).unwrap();
+                    println!(
+                        "{} versions deleted in domain {}.",
+                        deleted.len(),
+                        domain.name
+                    );
+                }
+            }
+            "restore" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+                let directory = Path::new(args.value_of("Directory").unwrap());
+
+                match domain.restore_snapshot(
+                    directory,
+                    args.value_of("UUID"),
+                    args.value_of("Version Name").unwrap(),
+                    args.is_present("Enable"),
+                ) {
+                    Ok(version) => println!(
+                        "The version {} ({}) has been restored in domain {}.",
+                        version.name, version.uuid, domain.name
+                    ),
+                    Err(e) => eprintln!("Couldn't restore the snapshot: {:?}", e),
+                }
+            }
+            "dnssec" => {
+                let args = matches.subcommand_matches(subcommand).unwrap();
+                let mut domain = domain.clone();
+
+                match args.value_of("Action").unwrap() {
+                    "status" => match domain.dnssec_enabled() {
+                        Ok(true) => println!("DNSSEC is enabled for domain {}.", domain.name),
+                        Ok(false) => println!("DNSSEC is disabled for domain {}.", domain.name),
+                        Err(e) => eprintln!("Couldn't query the DNSSEC status: {:?}", e),
+                    },
+                    "enable" => match domain.enable_dnssec() {
+                        Ok(()) => println!(
+                            "DNSSEC has been enabled for domain {}, don't forget to publish the DS records at your registrar.",
+                            domain.name
+                        ),
+                        Err(e) => eprintln!("Couldn't enable DNSSEC: {:?}", e),
+                    },
+                    "disable" => match domain.disable_dnssec() {
+                        Ok(()) => println!("DNSSEC has been disabled for domain {}.", domain.name),
+                        Err(e) => eprintln!("Couldn't disable DNSSEC: {:?}", e),
+                    },
+                    "ds" => {
+                        let keys = match domain.dnssec_keys() {
+                            Ok(keys) => keys,
+                            Err(e) => {
+                                eprintln!("Couldn't retrieve the DNSSEC keys: {:?}", e);
+                                return;
+                            }
+                        };
+                        if keys.is_empty() {
+                            println!("No DNSSEC key found for domain {}.", domain.name);
+                        }
+                        for key in keys {
+                            if let Some(r) = key.ds_record(&domain.name) {
+                                println!("{}", r);
+                            }
+                            if let Some(r) = key.dnskey_record(&domain.name) {
+                                println!("{}", r);
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
             _ => unreachable!(),
         }
+    } else if let Ok(Some((domain, _))) = accounts.clone().skip_external(false).resolve(&name) {
+        eprintln!(
+            "The domain {} is hosted outside of online.net, its zone can't be managed ! Exiting...",
+            domain.name
+        );
     } else {
         eprintln!("No domain name matching {} found ! Exiting...", record);
     }